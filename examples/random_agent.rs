@@ -0,0 +1,50 @@
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use rust_pacman::environment::{Action, Environment};
+use rust_pacman::maze::parse_maze;
+
+/*
+ * Plays a batch of seeded episodes with an agent that picks random actions, without opening a window
+ *
+ *  cargo run --example random_agent -- 100
+ */
+
+const TICKS_PER_STEP: u32 = 4;
+
+fn main() {
+    let episodes: u64 = std::env::args().nth(1).and_then(|arg| arg.parse().ok()).unwrap_or(10);
+
+    let source = std::fs::read_to_string("assets/levels/classic.maze").expect("could not read the maze file");
+    let maze = parse_maze(&source).expect("invalid maze file");
+
+    let mut environment = Environment::new(&maze, 0, TICKS_PER_STEP);
+    let mut total_reward = 0;
+
+    for seed in 0..episodes {
+        environment.reset(seed);
+        let mut agent = StdRng::seed_from_u64(seed);
+        let mut action = Action::None;
+        let mut episode_reward = 0;
+        let mut steps = 0;
+
+        loop {
+            // stick with an action for a while so the agent actually gets somewhere
+            if agent.gen_bool(0.1) {
+                action = Action::ALL[agent.gen_range(0..Action::ALL.len())];
+            }
+
+            let step = environment.step(action);
+            episode_reward += step.reward;
+            steps += 1;
+
+            if step.done {
+                println!("episode {}: reward {} in {} steps, {} tokens left", seed, episode_reward, steps, step.observation.tokens_left);
+                break;
+            }
+        }
+
+        total_reward += episode_reward;
+    }
+
+    println!("mean reward over {} episodes: {:.1}", episodes, total_reward as f64 / episodes.max(1) as f64);
+}
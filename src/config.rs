@@ -0,0 +1,28 @@
+use std::{fs::File, io::{prelude::*, BufReader}};
+
+/*
+ * config.txt in the working directory holds "key: value" lines for things that can be set without rebuilding
+ *
+ *  seed: 1234
+ *  extra_life: 10000
+ *  extra_life_every: 20000
+ *  frightened: flee
+ *  ghost_combo: 200 400 800 1600
+ *
+ * ghost_combo is the points for each ghost eaten in a row on one ghost weakness token, the last one is used for any
+ * after that
+ */
+
+pub const CONFIG_FILE: &str = "config.txt";
+
+/*
+ * The value for the given key in the config file, None if the file or key is missing
+ */
+pub fn config_value(key: &str) -> Option<String> {
+    let file = File::open(CONFIG_FILE).ok()?;
+
+    BufReader::new(file).lines().map_while(Result::ok).find_map(|line| {
+        let (line_key, value) = line.split_once(':')?;
+        (line_key.trim() == key).then(|| value.trim().to_string())
+    })
+}
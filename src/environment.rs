@@ -0,0 +1,326 @@
+use std::time::Duration;
+
+use bevy::math::Vec2;
+
+use crate::fixedtimestep::SIMULATION_HZ;
+use crate::gamelogic::{BlockCell, Direction, GameLogic, Horizontal, Vertical};
+use crate::ghost::{FrightenedBehaviour, GhostActionsStatus};
+use crate::maze::Maze;
+use crate::simulation::{Simulation, SimulationStatus};
+
+/*
+ * Environment for training agents to play the game
+ *
+ * Wraps the headless simulation in the usual reset / step / observe interface. Each step holds the chosen action down
+ * for a fixed number of simulation ticks, the reward is the points scored during the step and the episode is done
+ * as soon as the player is caught or the level is cleared. Everything is seeded, so the same seed and actions always
+ * give the same episode.
+ *
+ *  let mut environment = Environment::new(&maze, seed, 4);
+ *  let mut observation = environment.reset(seed);
+ *  loop {
+ *      let step = environment.step(agent.choose(&observation));
+ *      ...
+ *      if step.done { break; }
+ *      observation = step.observation;
+ *  }
+ */
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    None,
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Action {
+    pub const ALL: [Action; 5] = [Action::None, Action::Up, Action::Down, Action::Left, Action::Right];
+
+    pub fn direction(&self) -> Direction {
+        match self {
+            Action::None => Direction::ZERO,
+            Action::Up => Direction { vertical: Vertical::Up, horizontal: Horizontal::Zero },
+            Action::Down => Direction { vertical: Vertical::Down, horizontal: Horizontal::Zero },
+            Action::Left => Direction { vertical: Vertical::Zero, horizontal: Horizontal::Left },
+            Action::Right => Direction { vertical: Vertical::Zero, horizontal: Horizontal::Right },
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GhostObservation {
+    pub name: String,
+    pub position: Vec2,
+    pub status: GhostActionsStatus,
+}
+
+/*
+ * Everything an agent can see - all positions are gameboard coordinates (col, row)
+ */
+#[derive(Clone)]
+pub struct Observation {
+    pub width: usize,
+    pub height: usize,
+    // all of the cells row by row, eaten tokens have already been removed
+    pub grid: Vec<BlockCell>,
+    pub player_position: Vec2,
+    pub ghosts: Vec<GhostObservation>,
+    pub tokens_left: usize,
+    // the bonus fruit, while there is one on the board
+    pub fruit_position: Option<Vec2>,
+}
+
+pub struct StepResult {
+    pub observation: Observation,
+    pub reward: i32,
+    pub done: bool,
+}
+
+pub struct Environment {
+    board: GameLogic,
+    ticks_per_step: u32,
+    frightened_behaviour: FrightenedBehaviour,
+    simulation: Simulation,
+}
+
+impl Environment {
+    pub fn new(maze: &Maze, seed: u64, ticks_per_step: u32) -> Self {
+        let board = GameLogic::from_maze(maze);
+
+        Environment {
+            simulation: Simulation::new(board.clone(), seed),
+            board,
+            ticks_per_step: ticks_per_step.max(1),
+            frightened_behaviour: FrightenedBehaviour::default(),
+        }
+    }
+
+    /*
+     * Change how weakened ghosts behave, from the current episode on
+     */
+    pub fn set_frightened_behaviour(&mut self, behaviour: FrightenedBehaviour) {
+        self.frightened_behaviour = behaviour;
+        self.simulation.frightened_behaviour = behaviour;
+    }
+
+    /*
+     * Start a new episode from the beginning of the level
+     */
+    pub fn reset(&mut self, seed: u64) -> Observation {
+        self.simulation = Simulation::new(self.board.clone(), seed);
+        self.simulation.frightened_behaviour = self.frightened_behaviour;
+        self.observe()
+    }
+
+    /*
+     * Hold the action down for the next few ticks
+     */
+    pub fn step(&mut self, action: Action) -> StepResult {
+        let start_score = self.simulation.score;
+        let tick_length = Duration::from_secs_f64(1.0 / SIMULATION_HZ);
+
+        for _ in 0..self.ticks_per_step {
+            if self.done() {
+                break;
+            }
+            self.simulation.tick(action.direction(), tick_length);
+        }
+
+        StepResult {
+            observation: self.observe(),
+            reward: self.simulation.score - start_score,
+            done: self.done(),
+        }
+    }
+
+    pub fn observe(&self) -> Observation {
+        let board = &self.simulation.board;
+
+        Observation {
+            width: board.width,
+            height: board.height,
+            grid: board.game_blocks.clone(),
+            player_position: self.simulation.players[0].position,
+            ghosts: self.simulation.ghosts.iter().map(|ghost| GhostObservation {
+                name: ghost.name.clone(),
+                position: ghost.position,
+                status: ghost.actions_status,
+            }).collect(),
+            tokens_left: self.simulation.tokens_left(),
+            fruit_position: self.simulation.fruit.as_ref().map(|fruit| fruit.position),
+        }
+    }
+
+    /*
+     * The episode is over once the player has been caught or has cleared the level
+     */
+    pub fn done(&self) -> bool {
+        self.simulation.status != SimulationStatus::Playing
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+    use crate::gamelogic::BlockReward;
+    use crate::maze::parse_maze;
+
+    // a loop around the pen, the ghosts come out at the top and the player starts at the bottom
+    const LOOP_MAZE: &str = "width: 11
+height: 7
+player_start: 5, 5
+pen_exit: 5, 1
+pen_centre: 5, 3
+---
+###########
+#o.......o#
+#.###=###.#
+#.#######.#
+#.#######.#
+#...._....#
+###########
+";
+
+    // the same loop with one token left, just to the right of the start
+    const LAST_TOKEN_MAZE: &str = "width: 11
+height: 7
+player_start: 5, 5
+pen_exit: 5, 1
+pen_centre: 5, 3
+---
+###########
+#_________#
+#_###=###_#
+#_#######_#
+#_#######_#
+#_____.___#
+###########
+";
+
+    fn environment(source: &str, seed: u64, ticks_per_step: u32) -> Environment {
+        Environment::new(&parse_maze(source).unwrap(), seed, ticks_per_step)
+    }
+
+    // everything in an observation, in a form that can be compared
+    fn describe(observation: &Observation) -> String {
+        let rewards: Vec<BlockReward> = observation.grid.iter().map(|cell| cell.block_reward).collect();
+        format!("{:?} {:?} {} {:?} {:?}", observation.player_position, observation.ghosts, observation.tokens_left,
+            observation.fruit_position, rewards)
+    }
+
+    // random actions that are held for a while, so the player gets somewhere
+    fn random_actions(seed: u64, count: usize) -> Vec<Action> {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut action = Action::None;
+        (0..count).map(|_| {
+            if rng.gen_bool(0.2) {
+                action = Action::ALL[rng.gen_range(0..Action::ALL.len())];
+            }
+            action
+        }).collect()
+    }
+
+    #[test]
+    fn same_seed_and_actions_give_the_same_episode() {
+        let play = |seed: u64| {
+            let mut environment = environment(LOOP_MAZE, 0, 4);
+            let mut episode = vec![(describe(&environment.reset(seed)), 0, false)];
+
+            for action in random_actions(seed, 1000) {
+                let step = environment.step(action);
+                episode.push((describe(&step.observation), step.reward, step.done));
+                if step.done {
+                    break;
+                }
+            }
+            episode
+        };
+
+        for seed in 0..10 {
+            assert_eq!(play(seed), play(seed), "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn reward_is_the_score_gained_during_the_step() {
+        let mut environment = environment(LOOP_MAZE, 3, 4);
+        let mut total = 0;
+
+        for action in random_actions(3, 1000) {
+            let score = environment.simulation.score;
+            let step = environment.step(action);
+            assert_eq!(step.reward, environment.simulation.score - score);
+            total += step.reward;
+            if step.done {
+                break;
+            }
+        }
+
+        assert!(total > 0);
+        assert_eq!(total, environment.simulation.score);
+    }
+
+    #[test]
+    fn done_when_the_player_is_caught() {
+        let mut environment = environment(LOOP_MAZE, 1, 4);
+        assert!(!environment.step(Action::None).done);
+
+        // put a chasing ghost right on top of the player
+        let simulation = &mut environment.simulation;
+        simulation.ghosts[0].actions_status = GhostActionsStatus::SearchingForPlayer;
+        simulation.ghosts[0].position = simulation.players[0].position;
+
+        let step = environment.step(Action::None);
+        assert!(step.done);
+        assert!(environment.done());
+        assert_eq!(environment.simulation.status, SimulationStatus::PlayerCaught);
+    }
+
+    #[test]
+    fn done_when_the_level_is_cleared() {
+        let mut environment = environment(LAST_TOKEN_MAZE, 1, 4);
+        assert_eq!(environment.observe().tokens_left, 1);
+
+        let mut step = environment.step(Action::Right);
+        for _ in 0..50 {
+            if step.done {
+                break;
+            }
+            step = environment.step(Action::Right);
+        }
+
+        assert!(step.done);
+        assert_eq!(step.observation.tokens_left, 0);
+        assert_eq!(step.reward, environment.simulation.scoring.point_token);
+        assert_eq!(environment.simulation.status, SimulationStatus::LevelCleared);
+    }
+
+    #[test]
+    fn step_advances_ticks_per_step_ticks() {
+        for ticks_per_step in [1, 4, 10] {
+            let mut environment = environment(LOOP_MAZE, 1, ticks_per_step);
+
+            for step in 1..=20 {
+                environment.step(Action::Left);
+                assert!(!environment.done());
+                assert_eq!(environment.simulation.ticks, step * ticks_per_step as usize);
+            }
+        }
+
+        // nothing more happens once the episode is over
+        let mut environment = environment(LOOP_MAZE, 1, 4);
+        environment.simulation.ghosts[0].actions_status = GhostActionsStatus::SearchingForPlayer;
+        environment.simulation.ghosts[0].position = environment.simulation.players[0].position;
+        environment.step(Action::None);
+        let ticks = environment.simulation.ticks;
+
+        let step = environment.step(Action::Left);
+        assert!(step.done);
+        assert_eq!(step.reward, 0);
+        assert_eq!(environment.simulation.ticks, ticks);
+    }
+}
@@ -0,0 +1,114 @@
+use bevy::prelude::*;
+
+use crate::config::{config_value, CONFIG_FILE};
+use crate::players::Players;
+use crate::ui::{flash_heart, spawn_heart, HeartContainer};
+use crate::{LivesLeft, Score, MAX_LIVES};
+
+/*
+ * Extra lives
+ *
+ * The player gets an extra life once their score reaches a threshold, and optionally another every so many points
+ * after that. By default it is the arcade rule of a single extra life at 10,000 points, config.txt can change it:
+ *
+ *  extra_life: 10000           score for the first extra life, 0 turns extra lives off
+ *  extra_life_every: 20000     points between each extra life after the first, leave out for only the one
+ *
+ * In co-op with a score each, the extra lives go to the shared lives from both scores added together.
+ */
+
+const DEFAULT_EXTRA_LIFE_SCORE: i32 = 10_000;
+
+#[derive(Resource)]
+pub struct ExtraLifeRule {
+    pub first: i32,
+    pub every: Option<i32>,
+}
+
+impl Default for ExtraLifeRule {
+    fn default() -> Self {
+        ExtraLifeRule { first: DEFAULT_EXTRA_LIFE_SCORE, every: None }
+    }
+}
+
+impl ExtraLifeRule {
+    /*
+     * How many extra lives the given score is worth in total
+     */
+    pub fn lives_earned(&self, score: i32) -> i32 {
+        if self.first <= 0 || score < self.first {
+            return 0;
+        }
+
+        match self.every {
+            Some(every) if every > 0 => 1 + (score - self.first) / every,
+            _ => 1,
+        }
+    }
+
+    fn from_config() -> Self {
+        let mut rule = ExtraLifeRule::default();
+
+        if let Some(value) = config_value("extra_life") {
+            match value.parse() {
+                Ok(first) => rule.first = first,
+                Err(_) => warn!("Ignoring invalid extra_life in {}: {}", CONFIG_FILE, value),
+            }
+        }
+        if let Some(value) = config_value("extra_life_every") {
+            match value.parse() {
+                Ok(every) => rule.every = Some(every),
+                Err(_) => warn!("Ignoring invalid extra_life_every in {}: {}", CONFIG_FILE, value),
+            }
+        }
+
+        rule
+    }
+}
+
+/*
+ * How many extra lives have been given out so far this game - it only goes back to zero when a new game is set up
+ * (see setup_gameboard), so a restarted level doesn't give the same lives out again when the score goes back past them
+ */
+#[derive(Resource, Default)]
+pub struct ExtraLivesAwarded(pub i32);
+
+pub struct ExtraLifePlugin;
+
+impl Plugin for ExtraLifePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ExtraLifeRule::from_config())
+            .init_resource::<ExtraLivesAwarded>()
+            .add_systems(Update, award_extra_lives.run_if(resource_changed::<Score>.or_else(resource_changed::<Players>)));
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn award_extra_lives(
+    mut commands: Commands,
+    score: Res<Score>,
+    players: Res<Players>,
+    rule: Res<ExtraLifeRule>,
+    mut awarded: ResMut<ExtraLivesAwarded>,
+    mut lives_left: ResMut<LivesLeft>,
+    heart_container: Query<Entity, With<HeartContainer>>,
+    asset_server: Res<AssetServer>,
+) {
+    let score = score.0 + players.partner_score.unwrap_or(0);
+    let earned = rule.lives_earned(score);
+
+    while awarded.0 < earned {
+        awarded.0 += 1;
+        info!("Extra life at {} points", score);
+
+        if lives_left.0 >= MAX_LIVES {
+            continue;
+        }
+        lives_left.0 += 1;
+
+        if let Ok(heart_container) = heart_container.get_single() {
+            let heart = spawn_heart(&mut commands, heart_container, &asset_server, lives_left.0 - 1);
+            flash_heart(&mut commands, heart);
+        }
+    }
+}
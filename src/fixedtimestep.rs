@@ -0,0 +1,96 @@
+use bevy::prelude::*;
+
+use crate::gamelogic::GameLogic;
+
+/*
+ * Fixed timestep simulation
+ *
+ * The player and ghosts are moved in FixedUpdate so every tick moves them the same distance no matter the frame rate,
+ * a slow frame just runs more ticks instead of letting something jump past a decision point. The tick rate and the
+ * frame rate don't line up, so anything with an Interpolated component is drawn part way between where it was on
+ * the last two ticks.
+ *
+ * The transform holds the simulated position while the fixed systems run and the drawn position the rest of the time.
+ */
+
+pub const SIMULATION_HZ: f64 = 60.0;
+
+#[derive(Component)]
+pub struct Interpolated {
+    // simulated screen position on the tick before last and the last tick
+    previous: Vec2,
+    current: Vec2,
+}
+
+impl Interpolated {
+    pub fn new(position: Vec2) -> Self {
+        Interpolated { previous: position, current: position }
+    }
+}
+
+/*
+ * The order things happen in each tick - the input for the tick is worked out, the simulation moves on and then the
+ * sprites are moved to match it
+ */
+#[derive(SystemSet, Clone, PartialEq, Eq, Hash, Debug)]
+pub enum SimulationSet {
+    Input,
+    Simulate,
+    UpdateSprites,
+}
+
+pub struct FixedTimestepPlugin;
+
+impl Plugin for FixedTimestepPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .insert_resource(Time::<Fixed>::from_hz(SIMULATION_HZ))
+            .configure_sets(FixedUpdate, (SimulationSet::Input, SimulationSet::Simulate, SimulationSet::UpdateSprites).chain())
+            .add_systems(FixedPreUpdate, restore_simulated_positions)
+            .add_systems(FixedPostUpdate, store_simulated_positions)
+            .add_systems(Update, interpolate_positions);
+    }
+}
+
+/*
+ * Put the transforms back to where the simulation left them before the next tick
+ */
+fn restore_simulated_positions(
+    mut interpolated: Query<(&mut Transform, &mut Interpolated)>,
+) {
+    for (mut transform, mut interpolated) in &mut interpolated {
+        interpolated.previous = interpolated.current;
+        transform.translation.x = interpolated.current.x;
+        transform.translation.y = interpolated.current.y;
+    }
+}
+
+fn store_simulated_positions(
+    mut interpolated: Query<(&Transform, &mut Interpolated)>,
+    game_logic: Query<&GameLogic>,
+) {
+    // nothing moves more than a tile in one tick unless it has gone through a warp
+    let max_step = game_logic.get_single().map_or(f32::MAX, |game_logic| game_logic.tile_size);
+
+    for (transform, mut interpolated) in &mut interpolated {
+        interpolated.current = transform.translation.truncate();
+
+        // don't draw anything sliding across the board on its way through a warp
+        if interpolated.previous.distance(interpolated.current) > max_step {
+            interpolated.previous = interpolated.current;
+        }
+    }
+}
+
+fn interpolate_positions(
+    fixed_time: Res<Time<Fixed>>,
+    mut interpolated: Query<(&mut Transform, &Interpolated)>,
+) {
+    let fraction = fixed_time.overstep_fraction();
+
+    for (mut transform, interpolated) in &mut interpolated {
+        let position = interpolated.previous.lerp(interpolated.current, fraction);
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+    }
+}
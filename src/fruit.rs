@@ -0,0 +1,69 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+/*
+ * Bonus fruit
+ *
+ * A piece of fruit appears on the board twice a level, once a set number of point tokens have been eaten, and is
+ * only there for a short while. Which fruit it is (and so what it is worth) comes from the level table, as in the arcade.
+ */
+
+// number of tokens eaten in a level when each fruit appears
+pub const FRUIT_TOKENS_EATEN: [usize; 2] = [70, 170];
+
+// how long the fruit stays on the board for, in seconds
+pub const FRUIT_TIME: f32 = 9.5;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Fruit {
+    Cherry,
+    Strawberry,
+    Orange,
+    Apple,
+    Melon,
+    Galaxian,
+    Bell,
+    Key,
+}
+
+impl Fruit {
+    pub const ALL: [Fruit; 8] = [Fruit::Cherry, Fruit::Strawberry, Fruit::Orange, Fruit::Apple, Fruit::Melon, Fruit::Galaxian, Fruit::Bell, Fruit::Key];
+
+    pub fn points(&self) -> i32 {
+        match self {
+            Fruit::Cherry => 100,
+            Fruit::Strawberry => 300,
+            Fruit::Orange => 500,
+            Fruit::Apple => 700,
+            Fruit::Melon => 1000,
+            Fruit::Galaxian => 2000,
+            Fruit::Bell => 3000,
+            Fruit::Key => 5000,
+        }
+    }
+}
+
+/*
+ * A fruit that is on the board waiting to be eaten
+ */
+#[derive(Clone)]
+pub struct BonusFruit {
+    pub fruit: Fruit,
+    // gameboard position
+    pub position: Vec2,
+    time_left: Timer,
+}
+
+impl BonusFruit {
+    pub fn new(fruit: Fruit, position: Vec2) -> Self {
+        BonusFruit { fruit, position, time_left: Timer::from_seconds(FRUIT_TIME, TimerMode::Once) }
+    }
+
+    /*
+     * Returns true once the fruit has been on the board for too long
+     */
+    pub fn tick(&mut self, delta: Duration) -> bool {
+        self.time_left.tick(delta).finished()
+    }
+}
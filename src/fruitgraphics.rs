@@ -0,0 +1,253 @@
+use bevy::{prelude::*, render::{render_asset::RenderAssetUsages, render_resource::{Extent3d, TextureDimension, TextureFormat}}, utils::HashMap};
+
+use crate::fixedtimestep::SimulationSet;
+use crate::fruit::Fruit;
+use crate::gamelogic::{GameLogic, GameSimulation, OnGameplayScreen};
+
+/*
+ * Bonus fruit graphics
+ *
+ * There are no fruit images in the assets so each fruit is drawn from a small bit of pixel art below when the game
+ * starts. The same images are used for the fruit on the board and for the fruit history in the UI.
+ */
+
+const FRUIT_PX: usize = 12;
+
+// size of the fruit on the board, in tiles
+const FRUIT_SPRITE_SIZE: f32 = 1.4;
+
+/*
+ * Pixel art for each fruit, one character per pixel
+ *
+ *  .   transparent
+ *  r   red         o   orange      y   yellow      w   white
+ *  g   green       l   light green b   brown       c   cyan        n   navy
+ */
+fn fruit_pixels(fruit: Fruit) -> [&'static str; FRUIT_PX] {
+    match fruit {
+        Fruit::Cherry => [
+            "........gg..",
+            "......gg.g..",
+            ".....g...g..",
+            "....g....g..",
+            "...g.....g..",
+            ".rrr....g...",
+            "rrrrr..rrr..",
+            "rrwrr.rrrrr.",
+            "rrrrr.rrwrr.",
+            ".rrr..rrrrr.",
+            ".......rrr..",
+            "............",
+        ],
+        Fruit::Strawberry => [
+            ".....gg.....",
+            "...gggggg...",
+            "..rrrggrrr..",
+            ".rrwrrrrwrr.",
+            ".rrrrrwrrrr.",
+            ".rwrrrrrrwr.",
+            ".rrrrwrrrrr.",
+            "..rrrrrrwr..",
+            "..rwrrrrrr..",
+            "...rrrwrr...",
+            "....rrrr....",
+            ".....rr.....",
+        ],
+        Fruit::Orange => [
+            ".....g......",
+            "....gggg....",
+            "...ooggooo..",
+            "..oooooooo..",
+            ".oooooooooo.",
+            ".owooooooooo",
+            ".owooooooooo",
+            ".oooooooooo.",
+            ".oooooooooo.",
+            "..oooooooo..",
+            "...oooooo...",
+            "............",
+        ],
+        Fruit::Apple => [
+            "......b.....",
+            ".....b......",
+            "..rrrbrrr...",
+            ".rrrrrrrrr..",
+            "rrwrrrrrrrr.",
+            "rwrrrrrrrrr.",
+            "rrrrrrrrrrr.",
+            "rrrrrrrrrrr.",
+            ".rrrrrrrrr..",
+            ".rrrrrrrrr..",
+            "..rrr.rrr...",
+            "............",
+        ],
+        Fruit::Melon => [
+            ".....b......",
+            "....gggg....",
+            "..gglgglgg..",
+            ".glgggglggg.",
+            ".gglggggllg.",
+            "glggglgggglg",
+            "gggglgggglgg",
+            ".gglggglggg.",
+            ".glgggglggg.",
+            "..ggglgggg..",
+            "....gggg....",
+            "............",
+        ],
+        Fruit::Galaxian => [
+            ".....yy.....",
+            ".....yy.....",
+            "....yyyy....",
+            ".n..yyyy..n.",
+            ".n.ryyyyr.n.",
+            ".nrrryyrrrn.",
+            ".nrrryyrrrn.",
+            ".n.rrrrrr.n.",
+            ".n..rrrr..n.",
+            ".n...rr...n.",
+            "............",
+            "............",
+        ],
+        Fruit::Bell => [
+            ".....yy.....",
+            "....yyyy....",
+            "...yyyyyy...",
+            "...yyyyyy...",
+            "..yyyyyyyy..",
+            "..ywyyyyyy..",
+            "..ywyyyyyy..",
+            ".yyyyyyyyyy.",
+            "yyyyyyyyyyyy",
+            ".cccccccccc.",
+            ".....ww.....",
+            "............",
+        ],
+        Fruit::Key => [
+            "....cccc....",
+            "...cc..cc...",
+            "...cccccc...",
+            ".....ww.....",
+            ".....ww.....",
+            ".....wwww...",
+            ".....ww.....",
+            ".....www....",
+            ".....ww.....",
+            ".....wwww...",
+            ".....ww.....",
+            "............",
+        ],
+    }
+}
+
+fn pixel_colour(pixel: char) -> [u8; 4] {
+    match pixel {
+        'r' => [255, 0, 0, 255],
+        'o' => [255, 160, 0, 255],
+        'y' => [255, 230, 0, 255],
+        'w' => [255, 255, 255, 255],
+        'g' => [0, 160, 40, 255],
+        'l' => [140, 230, 120, 255],
+        'b' => [150, 80, 20, 255],
+        'c' => [0, 220, 255, 255],
+        'n' => [40, 60, 220, 255],
+        _ => [0, 0, 0, 0],
+    }
+}
+
+pub fn build_fruit_image(fruit: Fruit) -> Image {
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: FRUIT_PX as u32,
+            height: FRUIT_PX as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 0],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+
+    for (row, line) in fruit_pixels(fruit).iter().enumerate() {
+        for (col, pixel) in line.chars().enumerate() {
+            let index = (row * FRUIT_PX + col) * 4;
+            image.data[index..index + 4].copy_from_slice(&pixel_colour(pixel));
+        }
+    }
+
+    image
+}
+
+/*
+ * The image for each of the fruit
+ */
+#[derive(Resource)]
+pub struct FruitImages(pub HashMap<Fruit, Handle<Image>>);
+
+#[derive(Component)]
+pub struct FruitSprite(pub Fruit);
+
+pub struct FruitGraphicsPlugin;
+
+impl Plugin for FruitGraphicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, build_fruit_images)
+            .add_systems(FixedUpdate, update_fruit_sprite.in_set(SimulationSet::UpdateSprites).run_if(resource_exists::<GameSimulation>));
+    }
+}
+
+fn build_fruit_images(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let fruit_images = Fruit::ALL.iter()
+        .map(|fruit| (*fruit, images.add(build_fruit_image(*fruit))))
+        .collect();
+
+    commands.insert_resource(FruitImages(fruit_images));
+}
+
+/*
+ * Put the fruit sprite out while there is a fruit on the board in the simulation, and take it away when there isn't
+ */
+fn update_fruit_sprite(
+    mut commands: Commands,
+    simulation: Res<GameSimulation>,
+    game_logic: Query<&GameLogic>,
+    fruit_sprites: Query<(Entity, &FruitSprite)>,
+    fruit_images: Res<FruitImages>,
+) {
+    let Ok(game_logic) = game_logic.get_single() else {
+        return;
+    };
+
+    let showing = fruit_sprites.get_single().ok().map(|(_, fruit_sprite)| fruit_sprite.0);
+    let on_board = simulation.fruit.as_ref().map(|fruit| fruit.fruit);
+    if showing == on_board {
+        return;
+    }
+
+    for (entity, _) in fruit_sprites.iter() {
+        commands.entity(entity).despawn();
+    }
+
+    let Some(fruit) = &simulation.fruit else {
+        return;
+    };
+
+    let screen_pos = game_logic.get_screen_coords(fruit.position.x, fruit.position.y);
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                custom_size: Some(Vec2::splat(game_logic.tile_size * FRUIT_SPRITE_SIZE)),
+                ..default()
+            },
+            // below the player and ghosts
+            transform: Transform::from_xyz(screen_pos.x, screen_pos.y, 0.005),
+            texture: fruit_images.0[&fruit.fruit].clone(),
+            ..default()
+        },
+        FruitSprite(fruit.fruit),
+        OnGameplayScreen,
+    ));
+}
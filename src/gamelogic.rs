@@ -1,12 +1,27 @@
 //use core::fmt;
 use std::time::Duration;
 
-use bevy::prelude::*;
+use bevy::{ecs::system::EntityCommands, prelude::*};
 
-use crate::ghost::{spawn_ghosts, Ghost, GhostBody, GhostEyes, GhostActionsStatus, GhostPositionStatus};
-use crate::{AnimationIndicies, AnimationTimer, ConsecutiveKills, LivesLeft, MultiColoured, Score};
+use crate::ghostsprites::{spawn_ghosts, GhostBody, GhostEyes};
+use crate::{AnimationIndicies, AnimationTimer, Level, LivesLeft, Score};
 
 use crate::gamestates::{despawn_screen, GameState};
+use crate::maze::{Maze, MazeHandle, ScatterCorners};
+use crate::mazegraphics::spawn_maze_graphics;
+use crate::fixedtimestep::{Interpolated, SimulationSet};
+use crate::gamerng::GameSeed;
+use crate::config::{config_value, CONFIG_FILE};
+use crate::ghost::FrightenedBehaviour;
+use crate::scorepopups::spawn_score_popup;
+use crate::replay::ReplayPlayback;
+use crate::inputactions::{ActionInput, InputAction};
+use crate::settings::Settings;
+use crate::gamestates::GameMode;
+use crate::players::{swap_players, Players, SwapPlayers};
+use crate::extralife::ExtraLivesAwarded;
+use crate::versus::{MatchWinner, VERSUS_GHOST};
+use crate::simulation::{PlayerState, Scoring, Simulation, SimulationEvent, SimulationStatus};
 
 //use crate::ui::HeartLife;
 
@@ -17,14 +32,15 @@ pub struct GameStartDelay (Timer);
 pub struct OnGameplayScreen;
 
 #[derive(Clone, Copy)]
-enum BlockType {
+pub enum BlockType {
     Wall,
+    PenDoor, // blocks the way like a wall, only ghosts move through it (to get in and out of the pen)
     Path,
     Warp(u8,u8),
 }
 
-#[derive(Clone, Copy)]
-enum BlockReward {
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum BlockReward {
     Nothing,
     PointToken,
     GhostWeaknessToken,
@@ -33,8 +49,8 @@ enum BlockReward {
 
 #[derive(Clone, Copy)]
 pub struct BlockCell {
-    block_type: BlockType,
-    block_reward: BlockReward,
+    pub block_type: BlockType,
+    pub block_reward: BlockReward,
 }
 
 #[derive(Clone, Copy, Eq, PartialEq, Debug)]
@@ -51,211 +67,393 @@ pub struct Direction {
     //pub horizontal: f32
 }
 
+impl Direction {
+    pub const ZERO: Direction = Direction {vertical: Vertical::Zero, horizontal: Horizontal::Zero};
+
+    pub fn opposite(&self) -> Direction {
+        Direction {
+            vertical:
+                if self.vertical == Vertical::Up { Vertical::Down }
+                else if self.vertical == Vertical::Down { Vertical::Up }
+                else { Vertical::Zero },
+            horizontal:
+                if self.horizontal == Horizontal::Left { Horizontal::Right }
+                else if self.horizontal == Horizontal::Right { Horizontal::Left }
+                else { Horizontal::Zero },
+        }
+    }
+}
+
+// the player sprite for the player in the simulation with this index
 #[derive(Component)]
-pub struct Player {
-    pub speed: f32,
-    pub direction_of_travel: Direction,
+pub struct Player(pub usize);
+
+// player two's sprite is tinted so the two can be told apart in co-op
+const PLAYER_TWO_COLOUR: Color = Color::srgb(0.4, 1.0, 0.6);
+
+// how long a player caught in co-op is off the board for before coming back, if there are lives to spare
+const CO_OP_RESPAWN_TIME: Duration = Duration::from_secs(3);
+
+/*
+ * The simulation for the game being played, everything on the gameplay screen is drawn from it
+ */
+#[derive(Resource, Deref, DerefMut)]
+pub struct GameSimulation(pub Simulation);
+
+/*
+ * How weakened ghosts behave, from "frightened: wander" or "frightened: flee" in config.txt
+ */
+#[derive(Resource, Default)]
+pub struct GhostFrightenedBehaviour(pub FrightenedBehaviour);
+
+impl GhostFrightenedBehaviour {
+    fn from_config() -> Self {
+        let Some(value) = config_value("frightened") else {
+            return GhostFrightenedBehaviour::default();
+        };
+
+        match FrightenedBehaviour::from_name(&value) {
+            Some(behaviour) => GhostFrightenedBehaviour(behaviour),
+            None => {
+                warn!("Ignoring invalid frightened in {}: {}", CONFIG_FILE, value);
+                GhostFrightenedBehaviour::default()
+            },
+        }
+    }
+}
+
+/*
+ * Points for each ghost eaten in a row, from a "ghost_combo: 200 400 800 1600" line in config.txt
+ */
+#[derive(Resource)]
+pub struct GhostComboPoints(pub Vec<i32>);
+
+impl Default for GhostComboPoints {
+    fn default() -> Self {
+        GhostComboPoints(Scoring::default().ghost_combo)
+    }
 }
 
+impl GhostComboPoints {
+    fn from_config() -> Self {
+        let Some(value) = config_value("ghost_combo") else {
+            return GhostComboPoints::default();
+        };
+
+        match Scoring::parse_ghost_combo(&value) {
+            Some(combo) => GhostComboPoints(combo),
+            None => {
+                warn!("Ignoring invalid ghost_combo in {}: {}", CONFIG_FILE, value);
+                GhostComboPoints::default()
+            },
+        }
+    }
+}
+
+/*
+ * Set while the level is being set up again after being restarted from the pause menu
+ */
+#[derive(Resource)]
+pub struct RestartLevel;
+
+/*
+ * The direction being held down for the next tick of the simulation
+ */
+#[derive(Resource)]
+pub struct TickInput(pub Direction);
+
+/*
+ * The direction player two is holding down for the next tick, when two players are on the board at once
+ */
+#[derive(Resource)]
+pub struct PlayerTwoTickInput(pub Direction);
+
 #[derive(Component, Deref, DerefMut)]
 pub struct LoseLife(Timer);
 
+// the animation for a player caught in co-op while the other carries on, gone once the timer finishes
+#[derive(Component, Deref, DerefMut)]
+pub struct PlayerCaughtAnimation(Timer);
+
 #[derive(Component)]
 pub struct PointTokenEntity;
 
 #[derive(Component)]
 pub struct GhostWeaknessEntity;
 
+// the gameboard cell a token sprite is drawn on
 #[derive(Component)]
+pub struct TokenCell(pub IVec2);
+
+#[derive(Component, Clone)]
 pub struct GameLogic {
-    pub game_blocks: [[BlockCell; BOARD_WIDTH]; BOARD_HEIGHT],
+    pub width: usize,
+    pub height: usize,
+    // all of the cells row by row, index with get_block
+    pub game_blocks: Vec<BlockCell>,
+
+    // size of a single cell on screen in px, and the screen position of the centre of cell (0, 0)
+    pub tile_size: f32,
+    pub origin: Vec2,
+
+    // gameboard positions used by the player and ghosts
+    pub player_start: Vec2,
+    pub pen_exit: Vec2,
+    pub pen_centre: Vec2,
+    pub scatter_corners: ScatterCorners,
+    pub fruit_position: Vec2,
 }
 
-const SCREEN_WIDTH_PX: f32 = 410.0;
-const SCREEN_HEIGHT_PX: f32 = 450.0;
+// tile size that all the sprites are drawn for, smaller tiles scale the sprites down
+pub const DEFAULT_TILE_SIZE: f32 = 15.0;
 
-const BOARD_WIDTH: usize = 26;
-const BOARD_HEIGHT: usize = 29;
+// the area of the window that the gameboard is drawn in (between the score bar and the fruit history)
+const PLAY_AREA_SIZE: Vec2 = Vec2::new(400.0, 446.0);
+const PLAY_AREA_CENTRE: Vec2 = Vec2::new(0.0, 0.0);
+
+impl GameLogic {
+    pub fn from_maze(maze: &Maze) -> Self {
+        // use the default tile size unless the maze is too big to fit in the play area
+        let tile_size = DEFAULT_TILE_SIZE
+            .min(PLAY_AREA_SIZE.x / maze.width as f32)
+            .min(PLAY_AREA_SIZE.y / maze.height as f32);
+
+        // centre the board in the play area (screen y goes up while rows go down)
+        let origin = Vec2 {
+            x: PLAY_AREA_CENTRE.x - ((maze.width as f32 - 1.0) * tile_size / 2.0),
+            y: PLAY_AREA_CENTRE.y + ((maze.height as f32 - 1.0) * tile_size / 2.0),
+        };
+
+        GameLogic {
+            width: maze.width,
+            height: maze.height,
+            game_blocks: maze.game_blocks.clone(),
+            tile_size,
+            origin,
+            player_start: maze.player_start,
+            pen_exit: maze.pen_exit,
+            pen_centre: maze.pen_centre,
+            scatter_corners: maze.scatter_corners,
+            fruit_position: maze.fruit_position,
+        }
+    }
+
+    /*
+     * Get the cell at the given col and row, None if it is off the gameboard
+     */
+    pub fn get_block(&self, col_index: i32, row_index: i32) -> Option<&BlockCell> {
+        if col_index >= 0 && (col_index as usize) < self.width &&
+           row_index >= 0 && (row_index as usize) < self.height {
+            self.game_blocks.get(row_index as usize * self.width + col_index as usize)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_block_mut(&mut self, col_index: i32, row_index: i32) -> Option<&mut BlockCell> {
+        if col_index >= 0 && (col_index as usize) < self.width &&
+           row_index >= 0 && (row_index as usize) < self.height {
+            self.game_blocks.get_mut(row_index as usize * self.width + col_index as usize)
+        } else {
+            None
+        }
+    }
+
+    /*
+     * How much sprites drawn for the default tile size need to be scaled by
+     */
+    pub fn tile_scale(&self) -> f32 {
+        self.tile_size / DEFAULT_TILE_SIZE
+    }
+
+    /*
+     * Get screen coords of the given col and row (gameboard position)
+     */
+    pub fn get_screen_coords(&self, col_index: f32, row_index: f32) -> Vec2 {
+        Vec2 {
+            x: self.origin.x + (col_index * self.tile_size),
+            y: self.origin.y - (row_index * self.tile_size),
+        }
+    }
+}
 
 pub struct GameLogicPlugin;
 
 impl Plugin for GameLogicPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GameState::LevelSetup), (setup_gameboard, setup_game_objects, move_to_gamestart).chain());
+        // a replay being played back sets the behaviour and scoring it was recorded with
+        if !app.world().contains_resource::<GhostFrightenedBehaviour>() {
+            app.insert_resource(GhostFrightenedBehaviour::from_config());
+        }
+        if !app.world().contains_resource::<GhostComboPoints>() {
+            app.insert_resource(GhostComboPoints::from_config());
+        }
+
+        app.add_systems(OnEnter(GameState::LevelSetup), (
+            despawn_screen::<OnGameplayScreen>.run_if(resource_exists::<RestartLevel>.or_else(resource_exists::<SwapPlayers>)),
+            swap_players.run_if(resource_exists::<SwapPlayers>),
+            setup_gameboard, setup_game_objects, spawn_ghosts, move_to_gamestart).chain());
         app.add_systems(OnEnter(GameState::GameStart), (setup_player_object, start_gamestart_timer).chain());
         app.add_systems(Update, gamestart_delay.run_if(in_state(GameState::GameStart)));
-        app.add_systems(Update, (player_movement, check_player_points_collision, check_player_weak_token_collision).run_if(in_state(GameState::Gameplay)));
+        app.add_systems(FixedUpdate, read_player_input.in_set(SimulationSet::Input).run_if(in_state(GameState::Gameplay)));
+        app.add_systems(FixedUpdate, run_simulation.in_set(SimulationSet::Simulate).run_if(in_state(GameState::Gameplay)));
+        app.add_systems(FixedUpdate, update_player_sprite.in_set(SimulationSet::UpdateSprites).run_if(resource_exists::<GameSimulation>));
         app.add_systems(Update, check_lose_life_animation.run_if(in_state(GameState::LoseLife)));
+        app.add_systems(Update, despawn_player_caught_animations);
         app.add_systems(OnEnter(GameState::GameOver), despawn_screen::<OnGameplayScreen>);
+        app.add_systems(OnEnter(GameState::MatchOver), despawn_screen::<OnGameplayScreen>);
+        // the game can be quit part way through from the pause menu
+        app.add_systems(OnEnter(GameState::MainMenu), despawn_screen::<OnGameplayScreen>);
         app.add_systems(OnEnter(GameState::LoseLife), (handle_lose_life, despawn_screen::<Player>).chain());
-        app.add_systems(OnExit(GameState::LoseLife), ((despawn_screen::<LoseLife>, despawn_screen::<GhostBody>, despawn_screen::<GhostEyes>), spawn_ghosts).chain());
+        app.add_systems(OnExit(GameState::LoseLife), ((despawn_screen::<LoseLife>, despawn_screen::<GhostBody>, despawn_screen::<GhostEyes>), reset_simulation_positions, spawn_ghosts).chain());
 
-        app.insert_resource(GameStartDelay {0: Timer::new(Duration::from_secs(3), TimerMode::Once)});
+        app.insert_resource(TickInput(Direction::ZERO));
+        app.insert_resource(PlayerTwoTickInput(Direction::ZERO));
+        app.insert_resource(GameStartDelay(Timer::new(Duration::from_secs(3), TimerMode::Once)));
     }
 }
 
 fn setup_player_object(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
-    mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>
-) {
-    let animation_indicies = AnimationIndicies {first: 0, last: 4};
-
-    commands.spawn((
-        SpriteBundle {
-            texture: asset_server.load("Pacman_SpriteSheet.png"),
-            transform: Transform::from_xyz(0.0, -40.0, 0.01),
-            ..default()
-        },
-        TextureAtlas {
-            layout: texture_atlases.add(
-                TextureAtlasLayout::from_grid(
-                    UVec2::new(22, 22),
-                    1, 5, None, None
-                )),
-            index: animation_indicies.first,
-        },
-        animation_indicies,
-        AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating)),
-        Player { speed: 6.0, direction_of_travel: Direction {vertical: Vertical::Zero, horizontal: Horizontal::Zero} },
-        OnGameplayScreen,
-    ));
-}
-
-fn setup_game_objects(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
+    game_logic: Query<&GameLogic>,
+    simulation: Res<GameSimulation>,
 ) {
-    let background_texture = asset_server.load("Background_single.png");
+    let game_logic = game_logic.single();
 
-    commands.spawn((
-        SpriteBundle {
-            sprite: Sprite {
-                custom_size: Some(Vec2::new(410.0, 456.0)),
-                color: Color::srgb(0.0, 0.0, 1.0),
+    for (index, player) in simulation.players.iter().enumerate() {
+        let animation_indicies = AnimationIndicies {first: 0, last: 4};
+        let start_pos = game_logic.get_screen_coords(player.position.x, player.position.y);
 
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite { color: player_colour(index), ..default() },
+                texture: asset_server.load("Pacman_SpriteSheet.png"),
+                transform: Transform::from_xyz(start_pos.x, start_pos.y, 0.01).with_scale(Vec3::splat(game_logic.tile_scale())),
                 ..default()
             },
-            transform: Transform {
-                translation: Vec3::new(0.0, -10.0, 0.0),
-                ..default()
+            TextureAtlas {
+                layout: texture_atlases.add(
+                    TextureAtlasLayout::from_grid(
+                        UVec2::new(22, 22),
+                        1, 5, None, None
+                    )),
+                index: animation_indicies.first,
             },
-            texture: background_texture,
-            ..default()
-        },
-        MultiColoured,
-        OnGameplayScreen,
-    ));
-    commands.spawn((
-        SpriteBundle {
-            sprite: Sprite {
-                custom_size: Some(Vec2::new(410.0, 456.0)), // same size and position as the background
-                color: Color::srgb(0.0, 1.0, 1.0),
+            animation_indicies,
+            AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating)),
+            Player(index),
+            Interpolated::new(start_pos),
+            OnGameplayScreen,
+        ));
+    }
+}
 
-                ..default()
-            },
-            transform: Transform {
-                translation: Vec3::new(0.0, -10.0, 0.05),
-                ..default()
-            },
-            texture: asset_server.load("warp_tunnels.png"),
-            ..default()
-        },
-        MultiColoured,
-        OnGameplayScreen
-    ));
+fn player_colour(player: usize) -> Color {
+    if player == 1 { PLAYER_TWO_COLOUR } else { Color::WHITE }
 }
 
+fn setup_game_objects(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    game_logic: Query<&GameLogic>,
+) {
+    // the walls are drawn from the gameboard so that any maze looks right
+    spawn_maze_graphics(&mut commands, &mut images, game_logic.single());
+}
+
+#[allow(clippy::too_many_arguments)]
 fn setup_gameboard(
     mut commands: Commands,
-    mut lives_left: ResMut<LivesLeft>,
+    (mut lives_left, mut extra_lives_awarded): (ResMut<LivesLeft>, ResMut<ExtraLivesAwarded>),
+    mut level: ResMut<Level>,
     asset_server: Res<AssetServer>,
+    maze_handle: Res<MazeHandle>,
+    mazes: Res<Assets<Maze>>,
+    simulation: Option<ResMut<GameSimulation>>,
+    seed: Res<GameSeed>,
+    frightened_behaviour: Res<GhostFrightenedBehaviour>,
+    ghost_combo: Res<GhostComboPoints>,
+    restart_level: Option<Res<RestartLevel>>,
+    swapping_players: Option<Res<SwapPlayers>>,
+    settings: Res<Settings>,
+    game_mode: Res<GameMode>,
+    playback: Option<Res<ReplayPlayback>>,
 ) {
-    if lives_left.0 == 0 {
-        lives_left.0 = 3;
-    }
-
-    let game_logic: GameLogic = GameLogic {
-    // initialise all the game blocks to default values (as a wall)
-        game_blocks: {
-            const W: BlockCell = BlockCell {block_type: BlockType::Wall, block_reward: BlockReward::Nothing};
-            const P: BlockCell = BlockCell {block_type: BlockType::Path, block_reward: BlockReward::PointToken};
-            const Q: BlockCell = BlockCell {block_type: BlockType::Path, block_reward: BlockReward::Nothing}; // a path but with no point token
-            const X: BlockCell = BlockCell {block_type: BlockType::Warp(25,13), block_reward: BlockReward::Nothing}; // X warps to Y
-            const Y: BlockCell = BlockCell {block_type: BlockType::Warp(0, 13), block_reward: BlockReward::Nothing}; // Y warps to X
-            const G: BlockCell = BlockCell {block_type: BlockType::Path, block_reward: BlockReward::GhostWeaknessToken}; // Ghost weakness token
-
-           [[P, P, P, P, P, P, P, P, P, P, P, P, W, W, P, P, P, P, P, P, P, P, P, P, P, P], // r0
-            [P, W, W, W, W, P, W, W, W, W, W, P, W, W, P, W, W, W, W, W, P, W, W, W, W, P], // r1
-            [G, W, W, W, W, P, W, W, W, W, W, P, W, W, P, W, W, W, W, W, P, W, W, W, W, G], // r2
-            [P, W, W, W, W, P, W, W, W, W, W, P, W, W, P, W, W, W, W, W, P, W, W, W, W, P], // r3
-            [P, P, P, P, P, P, P, P, P, P, P, P, P, P, P, P, P, P, P, P, P, P, P, P, P, P], // r4
-            [P, W, W, W, W, P, W, W, P, W, W, W, W, W, W, W, W, P, W, W, P, W, W, W, W, P], // r5
-            [P, W, W, W, W, P, W, W, P, W, W, W, W, W, W, W, W, P, W, W, P, W, W, W, W, P], // r6
-            [P, P, P, P, P, P, W, W, P, P, P, P, W, W, P, P, P, P, W, W, P, P, P, P, P, P], // r7
-            [W, W, W, W, W, P, W, W, W, W, W, P, W, W, P, W, W, W, W, W, P, W, W, W, W, W], // r8
-            [W, W, W, W, W, P, W, W, W, W, W, P, W, W, P, W, W, W, W, W, P, W, W, W, W, W], // r9
-            [W, W, W, W, W, P, W, W, P, P, P, P, P, P, P, P, P, P, W, W, P, W, W, W, W, W], // r10
-            [W, W, W, W, W, P, W, W, P, W, W, W, W, W, W, W, W, P, W, W, P, W, W, W, W, W], // r11
-            [W, W, W, W, W, P, W, W, P, W, W, W, W, W, W, W, W, P, W, W, P, W, W, W, W, W], // r12
-            [X, Q, P, P, P, P, P, P, P, W, W, W, W, W, W, W, W, P, P, P, P, P, P, P, Q, Y], // r13
-            [W, W, W, W, W, P, W, W, P, W, W, W, W, W, W, W, W, P, W, W, P, W, W, W, W, W], // r14
-            [W, W, W, W, W, P, W, W, P, W, W, W, W, W, W, W, W, P, W, W, P, W, W, W, W, W], // r15
-            [W, W, W, W, W, P, W, W, P, P, P, P, Q, Q, P, P, P, P, W, W, P, W, W, W, W, W], // r16
-            [W, W, W, W, W, P, W, W, P, W, W, W, W, W, W, W, W, P, W, W, P, W, W, W, W, W], // r17
-            [W, W, W, W, W, P, W, W, P, W, W, W, W, W, W, W, W, P, W, W, P, W, W, W, W, W], // r18
-            [P, P, P, P, P, P, P, P, P, P, P, P, W, W, P, P, P, P, P, P, P, P, P, P, P, P], // r19
-            [P, W, W, W, W, P, W, W, W, W, W, P, W, W, P, W, W, W, W, W, W, W, W, W, W, P], // r20
-            [P, W, W, W, W, P, W, W, W, W, W, P, W, W, P, W, W, W, W, W, W, W, W, W, W, P], // r21
-            [G, P, P, W, W, P, P, P, P, P, P, P, P, P, P, P, P, P, P, P, P, W, W, P, P, G], // r22
-            [W, W, P, W, W, P, W, W, P, W, W, W, W, W, W, W, W, P, W, W, P, W, W, P, W, W], // r23
-            [W, W, P, W, W, P, W, W, P, W, W, W, W, W, W, W, W, P, W, W, P, W, W, P, W, W], // r24
-            [P, P, P, P, P, P, W, W, P, P, P, P, W, W, P, P, P, P, W, W, P, P, P, P, P, P], // r25
-            [P, W, W, W, W, W, W, W, W, W, W, P, W, W, P, W, W, W, W, W, W, W, W, W, W, P], // r26
-            [P, W, W, W, W, W, W, W, W, W, W, P, W, W, P, W, W, W, W, W, W, W, W, W, W, P], // r27
-            [P, P, P, P, P, P, P, P, P, P, P, P, P, P, P, P, P, P, P, P, P, P, P, P, P, P]] // r28
-
-            // This is for testing a board that is easy to beat
-            /*[[Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, W, W, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q], // r0
-            [Q, W, W, W, W, Q, W, W, W, W, W, Q, W, W, Q, W, W, W, W, W, Q, W, W, W, W, Q], // r1
-            [Q, W, W, W, W, Q, W, W, W, W, W, Q, W, W, Q, W, W, W, W, W, Q, W, W, W, W, Q], // r2
-            [Q, W, W, W, W, Q, W, W, W, W, W, Q, W, W, Q, W, W, W, W, W, Q, W, W, W, W, Q], // r3
-            [Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q], // r4
-            [Q, W, W, W, W, Q, W, W, Q, W, W, W, W, W, W, W, W, Q, W, W, Q, W, W, W, W, Q], // r5
-            [Q, W, W, W, W, Q, W, W, Q, W, W, W, W, W, W, W, W, Q, W, W, Q, W, W, W, W, Q], // r6
-            [Q, Q, Q, Q, Q, Q, W, W, Q, Q, Q, Q, W, W, Q, Q, Q, Q, W, W, Q, Q, Q, Q, Q, Q], // r7
-            [W, W, W, W, W, Q, W, W, W, W, W, Q, W, W, Q, W, W, W, W, W, Q, W, W, W, W, W], // r8
-            [W, W, W, W, W, Q, W, W, W, W, W, Q, W, W, Q, W, W, W, W, W, Q, W, W, W, W, W], // r9
-            [W, W, W, W, W, Q, W, W, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, W, W, Q, W, W, W, W, W], // r10
-            [W, W, W, W, W, Q, W, W, Q, W, W, W, W, W, W, W, W, Q, W, W, Q, W, W, W, W, W], // r11
-            [W, W, W, W, W, Q, W, W, Q, W, W, W, W, W, W, W, W, Q, W, W, Q, W, W, W, W, W], // r12
-            [X, Q, Q, Q, Q, Q, Q, Q, Q, W, W, W, W, W, W, W, W, Q, Q, Q, Q, Q, Q, Q, Q, Y], // r13
-            [W, W, W, W, W, Q, W, W, Q, W, W, W, W, W, W, W, W, Q, W, W, Q, W, W, W, W, W], // r14
-            [W, W, W, W, W, Q, W, W, Q, W, W, W, W, W, W, W, W, Q, W, W, Q, W, W, W, W, W], // r15
-            [W, W, W, W, W, Q, W, W, Q, Q, P, P, Q, Q, P, P, Q, Q, W, W, Q, W, W, W, W, W], // r16
-            [W, W, W, W, W, Q, W, W, Q, W, W, W, W, W, W, W, W, Q, W, W, Q, W, W, W, W, W], // r17
-            [W, W, W, W, W, Q, W, W, Q, W, W, W, W, W, W, W, W, Q, W, W, Q, W, W, W, W, W], // r18
-            [Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, W, W, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q], // r19
-            [Q, W, W, W, W, Q, W, W, W, W, W, Q, W, W, Q, W, W, W, W, W, W, W, W, W, W, Q], // r20
-            [Q, W, W, W, W, Q, W, W, W, W, W, Q, W, W, Q, W, W, W, W, W, W, W, W, W, W, Q], // r21
-            [Q, Q, Q, W, W, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, W, W, Q, Q, G], // r22
-            [W, W, Q, W, W, Q, W, W, Q, W, W, W, W, W, W, W, W, Q, W, W, Q, W, W, Q, W, W], // r23
-            [W, W, Q, W, W, Q, W, W, Q, W, W, W, W, W, W, W, W, Q, W, W, Q, W, W, Q, W, W], // r24
-            [Q, Q, Q, Q, Q, Q, W, W, Q, Q, Q, Q, W, W, Q, Q, Q, Q, W, W, Q, Q, Q, Q, Q, Q], // r25
-            [Q, W, W, W, W, W, W, W, W, W, W, Q, W, W, Q, W, W, W, W, W, W, W, W, W, W, Q], // r26
-            [Q, W, W, W, W, W, W, W, W, W, W, Q, W, W, Q, W, W, W, W, W, W, W, W, W, W, Q], // r27
-            [Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q, Q]] // r28*/
-        }
+    // no lives left means a new game is starting
+    let new_game = lives_left.0 == 0;
+    // when restarting, the simulation has already been put back to the start of the level,
+    // and when swapping players the other player's simulation carries on from where they left it
+    let restarting = (restart_level.is_some() || swapping_players.is_some()) && !new_game;
+    if new_game {
+        lives_left.0 = settings.starting_lives;
+        level.0 = 1;
+        extra_lives_awarded.0 = 0;
+    } else if !restarting {
+        level.0 += 1;
+    }
+    commands.remove_resource::<RestartLevel>();
+    commands.remove_resource::<SwapPlayers>();
+
+    // the maze is only loaded by the time we get here - the main menu waits for it before letting the game start
+    let Some(maze) = mazes.get(&maze_handle.0) else {
+        error!("Maze has not been loaded, cannot set up the gameboard");
+        return;
     };
+    info!("Setting up maze: {} (level {})", maze.name, level.0);
 
-    // spawn the point token pattern based on the gameblocks
-    let mut row_index: u32 = 0;
-    let mut col_index: u32 = 0;
+    let game_logic = GameLogic::from_maze(maze);
+
+    let new_simulation = || {
+        let mut simulation = Simulation::new(game_logic.clone(), seed.0);
+        simulation.frightened_behaviour = frightened_behaviour.0;
+        simulation.scoring.ghost_combo = ghost_combo.0.clone();
+        simulation.movement = playback.as_ref().map_or(settings.movement, |playback| playback.movement());
+        simulation.set_difficulty(playback.as_ref().map_or(settings.difficulty, |playback| playback.difficulty()));
+        simulation
+    };
+
+    // the tokens still on the board - a player coming back to their turn only has the ones they left
+    let token_board = match simulation {
+        Some(simulation) if restarting => simulation.board.clone(),
+        Some(mut simulation) if !new_game => {
+            simulation.start_level(game_logic.clone(), level.0);
+            simulation.board.clone()
+        },
+        _ => {
+            // a replay is always a one player game
+            let game_mode = if playback.is_some() { GameMode::OnePlayer } else { *game_mode };
+
+            let mut simulation = new_simulation();
+            match game_mode {
+                GameMode::CoOp => simulation.add_player(),
+                GameMode::Versus => simulation.control_ghost(VERSUS_GHOST),
+                _ => {},
+            }
+            let token_board = simulation.board.clone();
+            commands.insert_resource(GameSimulation(simulation));
+
+            commands.insert_resource(match game_mode {
+                GameMode::OnePlayer | GameMode::Versus => Players::one_player(),
+                GameMode::TwoPlayer => Players::two_players(new_simulation(), settings.starting_lives),
+                GameMode::CoOp => Players::co_op(settings.split_scores),
+            });
 
-    for block_row in game_logic.game_blocks {
-        for block_cell in block_row {
+            token_board
+        },
+    };
+
+    let token_scale = game_logic.tile_scale();
+
+    // spawn the point token pattern based on the gameblocks
+    for row_index in 0..token_board.height {
+        for col_index in 0..token_board.width {
+            let Some(block_cell) = token_board.get_block(col_index as i32, row_index as i32) else {
+                continue;
+            };
             
-            let screen_coords = get_screen_coords(col_index as f32, row_index as f32);
+            let screen_coords = game_logic.get_screen_coords(col_index as f32, row_index as f32);
 
             match block_cell.block_reward {
                 BlockReward::PointToken => {
@@ -263,7 +461,7 @@ fn setup_gameboard(
                     // spawn a point token in the bevy commands
                     commands.spawn((SpriteBundle {
                         sprite: Sprite {
-                            custom_size: Some(Vec2::new(6.0, 6.0)),
+                            custom_size: Some(Vec2::new(6.0, 6.0) * token_scale),
                             ..default()
                         },
                         transform: Transform::from_xyz(
@@ -275,12 +473,13 @@ fn setup_gameboard(
                         ..default()
                     },
                     PointTokenEntity,
+                    TokenCell(IVec2::new(col_index as i32, row_index as i32)),
                     OnGameplayScreen));
                 },
                 BlockReward::GhostWeaknessToken => {
                     commands.spawn((SpriteBundle {
                         sprite: Sprite {
-                            custom_size: Some(Vec2::new(16.0, 16.0)),
+                            custom_size: Some(Vec2::new(16.0, 16.0) * token_scale),
                             ..default()
                         },
                         transform: Transform::from_xyz(
@@ -292,15 +491,12 @@ fn setup_gameboard(
                     },
                     
                     GhostWeaknessEntity,
+                    TokenCell(IVec2::new(col_index as i32, row_index as i32)),
                     OnGameplayScreen));
                 },
                 _ => (),
             }
-
-            col_index += 1;
         }
-        row_index += 1;
-        col_index = 0;
     }
     
     commands.spawn((
@@ -333,297 +529,247 @@ fn gamestart_delay(
     }
 }
 
-fn player_movement(
-    mut player_query: Query<(&mut Transform, &mut Player)>,
-    game_logic_query: Query<&GameLogic>,
-    input: Res<ButtonInput<KeyCode>>,
-    time: Res<Time>,
-) {
-    // extract the transform and the player objects from the query
-    let player_query_single = player_query.single_mut();
-    let mut transform = player_query_single.0;
-    let mut player = player_query_single.1;
-
-    let game_logic = game_logic_query.single();
-
-    let movement_amount = player.speed * time.delta_seconds();
-
-    /*struct InputDirection {
-        vertical: f32,
-        horizontal: f32,
-    }*/
-
-    let mut pressed_direction = Direction {vertical : Vertical::Zero, horizontal : Horizontal::Zero};
+/*
+ * Turn the direction actions being held down into a direction, given whether each action is held down and the up,
+ * down, left and right actions
+ */
+fn pressed_direction(pressed: impl Fn(InputAction) -> bool, [up, down, left, right]: [InputAction; 4]) -> Direction {
+    let mut pressed_direction = Direction::ZERO;
 
-    // convert keycode into a direction
-    if input.pressed(KeyCode::ArrowUp) {
+    // convert the actions into a direction
+    if pressed(up) {
         pressed_direction.vertical = Vertical::Up;
     }
-    if input.pressed(KeyCode::ArrowDown) {
+    if pressed(down) {
         pressed_direction.vertical = match pressed_direction.vertical {
             Vertical::Up => { Vertical::Zero }, // pressing up and down together cancel out
             _ => { Vertical::Down }
         }
     }
-    if input.pressed(KeyCode::ArrowRight) {
+    if pressed(right) {
         pressed_direction.horizontal = Horizontal::Right;
     }
-    if input.pressed(KeyCode::ArrowLeft) {
+    if pressed(left) {
         pressed_direction.horizontal = match pressed_direction.horizontal {
             Horizontal::Right => { Horizontal::Zero }, // pressing left and right together cancel out
             _ => { Horizontal::Left }
         }
     }
 
+    pressed_direction
+}
 
-    let current_pos = get_game_board_coords(Vec2{x: transform.translation.x, y: transform.translation.y});
-
-    let mut potential_pos: (Vec2, bool) = (Vec2{x:0.0, y:0.0}, true);
-
-    // compare pressed direction to current direction of travel (favor vertical direction changes)
-    if pressed_direction.vertical != Vertical::Zero && pressed_direction.vertical != player.direction_of_travel.vertical {
-        // we are only looking at the vertical direction here
-        // we have pressed the opposite direction to which we were moving before, try to move back the way we have come
-        //  or we are changing direction down a hallway 
-        //  or we have been sat not moving at all
-
-        let vertical_direction = Direction{vertical: pressed_direction.vertical, horizontal: Horizontal::Zero};
-
-        let mut skip_get_pos = false;
-
-        // if the player was not already going vertical (they are turning from a horizontal direction of travel to turn a 90 degree corner)
-        if player.direction_of_travel.vertical == Vertical::Zero {
-            // check if they are close enough to the center coordinate of a cell (only allow turning down a corridor if we are close enough to it)
-            //let rounded_pos = current_pos.round();
-            // don't let the turn happen if we are too far away from the center position -- ASSUMPTION: ALL CORRIDOORS ARE ONLY 1 BLOCK WIDE
-
-            skip_get_pos = !at_decision_point(current_pos, player.direction_of_travel);
-            potential_pos.1 = skip_get_pos;
-        }
-
-        // if no collision detected yet, get the new position
-        if skip_get_pos == false {
-            potential_pos = get_new_position_alt(game_logic, current_pos, vertical_direction, movement_amount);
-        }
-        
-        // check if there was any collision detected at all in this direction
-        if potential_pos.1 == false {
-
-            // set new player direction of travel
-            player.direction_of_travel = vertical_direction;
-
-            // snap horizontal position to the nearest whole number
-            potential_pos.0.x = potential_pos.0.x.round();
-        }
-    }
-    
-    // now check the horizontal direction if the vertical was not fruitful
-    if potential_pos.1 && pressed_direction.horizontal != Horizontal::Zero && pressed_direction.horizontal != player.direction_of_travel.horizontal {
-    
-        let horizontal_direction = Direction{vertical: Vertical::Zero, horizontal: pressed_direction.horizontal};
-        
-        let mut skip_get_pos = false;
-
-        // if the player was not already going horizontal (they are turning from a vertical direction of travel to turn a 90 degree corner)
-        if player.direction_of_travel.horizontal == Horizontal::Zero {
-            // check if they are close enough to the center coordinate of a cell (only allow turning down a corridor if we are close enough to it)
-            //let rounded_pos = current_pos.round();
-            // don't let the turn happen if we are too far away from the center position -- ASSUMPTION: ALL CORRIDOORS ARE ONLY 1 BLOCK WIDE
-
-            skip_get_pos = !at_decision_point(current_pos, player.direction_of_travel);
-            potential_pos.1 = skip_get_pos;
-        }
-
-        // if no collision detected yet, get the new position
-        if skip_get_pos == false {
-            potential_pos = get_new_position_alt(game_logic, current_pos, horizontal_direction, movement_amount);
-        }
-
-        // check if there was any collision detected at all in this direction
-        if potential_pos.1 == false {
-            // set new player direction of travel
-            player.direction_of_travel = horizontal_direction;
-
-            // snap vertical position to the nearest whole number
-            potential_pos.0.y = potential_pos.0.y.round();
-        }
-    }
-
-    if potential_pos.1 {
-        // neither of the player inputs directions are valid or are maybe are not present
-        // for now then we will try to continue in the same direction as we were moving before
-
-        potential_pos = get_new_position_alt(game_logic, current_pos,
-            player.direction_of_travel, movement_amount);
+pub fn read_player_input(
+    input: ActionInput,
+    simulation: Res<GameSimulation>,
+    mut tick_input: ResMut<TickInput>,
+    mut player_two_input: ResMut<PlayerTwoTickInput>,
+) {
+    if simulation.players.len() < 2 && simulation.human_ghost.is_none() {
+        tick_input.0 = pressed_direction(|action| input.pressed(action), InputAction::DIRECTIONS[0]);
+        return;
     }
 
-    if potential_pos.1 {
-        // we cannot move anymore - stop moving now!
-        player.direction_of_travel.horizontal = Horizontal::Zero;
-        player.direction_of_travel.vertical = Vertical::Zero;
-        
-        // snap to the block position so that we are directly on the path
-        let snapped_grid_pos = Vec2 {x: current_pos.x.round(), y: current_pos.y.round()};
-        let new_screen_pos = get_screen_coords(snapped_grid_pos.x, snapped_grid_pos.y);
-        transform.translation.x = new_screen_pos.x;
-        transform.translation.y = new_screen_pos.y;
-
-    } else {
-        // we have found a valid new position, move to this position
-        let screen_pos = get_screen_coords(potential_pos.0.x, potential_pos.0.y);
-        transform.translation.x = screen_pos.x;
-        transform.translation.y = screen_pos.y;
-
-        // make sure the player sprite is facing the current direction
-
-        // update the rotation of the sprite based on the direction it is moving
-        // create an angle from the direction:
-        // direction.horizontal = 1 = 0 degrees
-        // direction.horizontal = -1 = 180 degrees
-        // direction.vertical = -1 = 90 degrees
-        // direction.vertical = 1 = 270 degrees
-
-        // 0 - ((direction horizontal x 90 degrees) - 90)
-        // 360 - (direction vertical x 90 degrees) + 180
-        if player.direction_of_travel.horizontal != Horizontal::Zero || player.direction_of_travel.vertical != Vertical::Zero {
-
-            let rotation_h = 
-                if player.direction_of_travel.horizontal != Horizontal::Zero {
-                    0.0 - ((player.direction_of_travel.horizontal as i32 as f32 * 90.0) - 90.0)
-                } else {
-                    0.0
-                };
-            let rotation_v = 
-                if player.direction_of_travel.vertical != Vertical::Zero {
-                    (player.direction_of_travel.vertical as i32 as f32 * 90.0) + 180.0
-                } else {
-                    0.0
-                };
-            let rotation_degrees = rotation_h + rotation_v;
-
-            transform.rotation = Quat::from_rotation_z(f32::to_radians(rotation_degrees));
-
-            if rotation_degrees == 180.0 {
-                transform.rotate_x(std::f32::consts::PI); // flip along the x axis 180 degrees (so we are now seeing the 'back' of the image)
-                // - imagine it is a page of paper where the ink has seeped through perfectly
-            }
-        }
-    }
+    // with two players (or a player and a ghost) on the board each has their own keys and gamepad
+    tick_input.0 = pressed_direction(|action| input.pressed_by_player(action, 0), InputAction::DIRECTIONS[0]);
+    player_two_input.0 = pressed_direction(|action| input.pressed_by_player(action, 1), InputAction::DIRECTIONS[1]);
 }
 
-fn check_player_points_collision(
-    player_query: Query<&Transform, With<Player>>,
-    point_tokens_query: Query<(&Sprite, &Transform, Entity), With<PointTokenEntity>>,
-    weak_tokens_query: Query<&GhostWeaknessEntity>,
+/*
+ * Move the simulation on a tick and react to whatever happened in it
+ */
+#[allow(clippy::too_many_arguments)]
+fn run_simulation(
+    mut simulation: ResMut<GameSimulation>,
+    tick_input: Res<TickInput>,
+    player_two_input: Res<PlayerTwoTickInput>,
+    time: Res<Time>,
+    tokens: Query<(Entity, &TokenCell)>,
+    game_logic: Query<&GameLogic>,
     mut commands: Commands,
     mut score: ResMut<Score>,
+    mut players: ResMut<Players>,
+    mut lives_left: ResMut<LivesLeft>,
     mut game_state: ResMut<NextState<GameState>>,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
 ) {
-    if point_tokens_query.is_empty() {
-        if weak_tokens_query.is_empty() {
-            game_state.set(GameState::LevelComplete);
-        }
+    let Ok(game_logic) = game_logic.get_single() else {
+        return;
+    };
+
+    // in versus player two is steering a ghost rather than a second Pac-Man
+    let inputs = if simulation.human_ghost.is_some() {
+        simulation.steer_ghost(player_two_input.0);
+        vec![tick_input.0]
     } else {
-        let player = player_query.single();
-        let player_rect = Rect::from_center_size(Vec2 {x: player.translation.x, y: player.translation.y}, Vec2 {x: 21.0, y: 21.0});
+        vec![tick_input.0, player_two_input.0]
+    };
 
-        for (point_token_sprite, point_token_transform, point_token_entity) in point_tokens_query.iter() {
-            // check each object for a collision on the transforms
+    for event in simulation.tick_players(&inputs, time.delta()) {
+        match event {
+            SimulationEvent::TokenEaten { player, cell, reward, points } => {
+                players.add_points(&mut score, player, points);
 
-            let size = 
-            match point_token_sprite.custom_size {
-                Some(size) => size,
-                None => Vec2 {x: 1.0, y: 1.0}
-            };
+                if reward == BlockReward::GhostWeaknessToken {
+                    spawn_score_popup(&mut commands, game_logic.get_screen_coords(cell.x as f32, cell.y as f32), points);
+                }
 
-            if check_collision(
-                Rect::from_center_size(
-                    Vec2 {x: point_token_transform.translation.x, y: point_token_transform.translation.y},
-                    size), 
-                player_rect)
-            {
-                score.0 += 10;
-                // collision occured - remove the entity and add the associated points to the score
-                commands.entity(point_token_entity).despawn();
-            }
+                for (token_entity, token_cell) in tokens.iter() {
+                    if token_cell.0 == cell {
+                        commands.entity(token_entity).despawn();
+                    }
+                }
+            },
+            SimulationEvent::GhostEaten { player, position, points, .. } | SimulationEvent::FruitEaten { player, position, points, .. } => {
+                players.add_points(&mut score, player, points);
+                spawn_score_popup(&mut commands, game_logic.get_screen_coords(position.x, position.y), points);
+            },
+            SimulationEvent::PlayerCaught { .. } if simulation.status == SimulationStatus::PlayerCaught => {
+                game_state.set(GameState::LoseLife);
+            },
+            SimulationEvent::PlayerCaught { player } => {
+                // the other player is still going, so only this one leaves the board
+                let position = simulation.players[player].position;
+                let screen_pos = game_logic.get_screen_coords(position.x, position.y);
+                spawn_caught_animation(&mut commands, &asset_server, &mut texture_atlases, screen_pos, game_logic.tile_scale(), player)
+                    .insert((PlayerCaughtAnimation(Timer::from_seconds(1.2, TimerMode::Once)), OnGameplayScreen));
+
+                // the last life is kept for whoever is left, otherwise the caught player comes back for a life
+                if lives_left.0 > 1 {
+                    lives_left.0 -= 1;
+                    simulation.respawn_player(player, CO_OP_RESPAWN_TIME);
+                }
+            },
+            SimulationEvent::LevelCleared if simulation.human_ghost.is_some() => {
+                commands.insert_resource(MatchWinner::PacMan);
+                game_state.set(GameState::MatchOver);
+            },
+            SimulationEvent::LevelCleared => {
+                game_state.set(GameState::LevelComplete);
+            },
         }
     }
 }
 
-/*fn move_to_level_setup(
-    mut game_state: ResMut<NextState<GameState>>,
-) {
-    game_state.set(GameState::LevelSetup);
-}*/
-
-fn check_player_weak_token_collision(
-    player_query: Query<&Transform, With<Player>>,
-    weak_tokens_query: Query<(&Sprite, &Transform, Entity), With<GhostWeaknessEntity>>,
-    mut ghost_query: Query<&mut Ghost>,
-    mut ghost_body_sprites: Query<&mut Sprite, (With<GhostBody>, Without<GhostWeaknessEntity>)>,
-    mut score: ResMut<Score>,
-    mut consecutive_kills: ResMut<ConsecutiveKills>,
-    mut commands: Commands,
+/*
+ * Move the player sprite to where the player is in the simulation
+ */
+fn update_player_sprite(
+    simulation: Res<GameSimulation>,
+    mut player_query: Query<(&mut Transform, &mut Visibility, &Player)>,
+    game_logic: Query<&GameLogic>,
 ) {
-    let player = player_query.single();
-    let player_rect = Rect::from_center_size(Vec2 {x: player.translation.x, y: player.translation.y}, Vec2 {x: 21.0, y: 21.0});
-
-    for (weak_token_sprite, weak_token_transform, weak_token_entity) in weak_tokens_query.iter() {
-        // check each object for a collision on the transforms
-        let size = 
-        match weak_token_sprite.custom_size {
-            Some(size) => size,
-            None => Vec2 {x: 1.0, y: 1.0}
+    let Ok(game_logic) = game_logic.get_single() else {
+        return;
+    };
+
+    for (mut transform, mut visibility, player) in player_query.iter_mut() {
+        let Some(player) = simulation.players.get(player.0) else {
+            continue;
         };
 
-        if check_collision(
-            Rect::from_center_size(
-                Vec2 {x: weak_token_transform.translation.x, y: weak_token_transform.translation.y},
-                size), 
-            player_rect)
-        {
-            score.0 += 50;
-            // collision occured - remove the entity and add the associated points to the score
-            commands.entity(weak_token_entity).despawn();
+        // the player is hidden while the points for eating a ghost are shown, as in the arcade, and while they are
+        // off the board after being caught in co-op
+        *visibility = if simulation.frozen() || !player.in_play { Visibility::Hidden } else { Visibility::Inherited };
 
-            // reset the consecutive kills value
-            consecutive_kills.0 = 0;
+        point_player_sprite(&mut transform, game_logic, player);
+    }
+}
 
-            // send the ghosts into weakened mode
-            for mut ghost in ghost_query.iter_mut() {
-                if let GhostPositionStatus::OutAndAbout = ghost.position_status {
-                    ghost.actions_status = GhostActionsStatus::Weakened;
+/*
+ * Move a player sprite to where the player is and face it the way they are going
+ */
+fn point_player_sprite(transform: &mut Transform, game_logic: &GameLogic, player: &PlayerState) {
+    let screen_pos = game_logic.get_screen_coords(player.position.x, player.position.y);
+    transform.translation.x = screen_pos.x;
+    transform.translation.y = screen_pos.y;
+
+    // make sure the player sprite is facing the current direction
+
+    // update the rotation of the sprite based on the direction it is moving
+    // create an angle from the direction:
+    // direction.horizontal = 1 = 0 degrees
+    // direction.horizontal = -1 = 180 degrees
+    // direction.vertical = -1 = 90 degrees
+    // direction.vertical = 1 = 270 degrees
+
+    // 0 - ((direction horizontal x 90 degrees) - 90)
+    // 360 - (direction vertical x 90 degrees) + 180
+    if player.direction_of_travel.horizontal != Horizontal::Zero || player.direction_of_travel.vertical != Vertical::Zero {
+
+        let rotation_h = 
+            if player.direction_of_travel.horizontal != Horizontal::Zero {
+                0.0 - ((player.direction_of_travel.horizontal as i32 as f32 * 90.0) - 90.0)
+            } else {
+                0.0
+            };
+        let rotation_v = 
+            if player.direction_of_travel.vertical != Vertical::Zero {
+                (player.direction_of_travel.vertical as i32 as f32 * 90.0) + 180.0
+            } else {
+                0.0
+            };
+        let rotation_degrees = rotation_h + rotation_v;
 
-                    ghost.time_weakened = Some(Timer::from_seconds(8.0, TimerMode::Once));
+        transform.rotation = Quat::from_rotation_z(f32::to_radians(rotation_degrees));
 
-                    if let Ok(mut ghost_body) = ghost_body_sprites.get_mut(ghost.body_entity) {
-                        ghost_body.color = Color::srgb(0.082, 0.141, 0.380); // a dark navy colour
-                    }
-                }
-            }
+        if rotation_degrees == 180.0 {
+            transform.rotate_x(std::f32::consts::PI); // flip along the x axis 180 degrees (so we are now seeing the 'back' of the image)
+            // - imagine it is a page of paper where the ink has seeped through perfectly
         }
     }
 }
 
+fn reset_simulation_positions(
+    mut simulation: ResMut<GameSimulation>,
+) {
+    simulation.reset_positions();
+}
+
 fn handle_lose_life(
-    player: Query<&Transform, With<Player>>,
+    player: Query<(&Transform, &Player)>,
+    simulation: Res<GameSimulation>,
+    game_logic: Query<&GameLogic>,
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
 ) {
+    // the player caught last is the only one still on the board
+    let Some((player_transform, player)) = player.iter()
+        .find(|(_, player)| simulation.players.get(player.0).is_some_and(|state| state.in_play)) else {
+        return;
+    };
+
     // run the lose animation
+    spawn_caught_animation(&mut commands, &asset_server, &mut texture_atlases,
+        player_transform.translation.truncate(), game_logic.single().tile_scale(), player.0)
+        .insert(LoseLife(Timer::from_seconds(1.2, TimerMode::Once)));
+}
+
+/*
+ * Spawn the animation of a player being caught at the given screen position
+ */
+fn spawn_caught_animation<'a>(
+    commands: &'a mut Commands,
+    asset_server: &AssetServer,
+    texture_atlases: &mut Assets<TextureAtlasLayout>,
+    screen_pos: Vec2,
+    tile_scale: f32,
+    player: usize,
+) -> EntityCommands<'a> {
     let animation_indicies = AnimationIndicies {first: 0, last: 4};
 
     //let mut pac_sprite = TextureAtlasSprite ::new(animation_indicies.first);
     //pac_sprite.custom_size = Some(Vec2::new(21.0, 20.0)); // had to do this because the sprite was showing one pixel row too many (first row of next frame)
 
-    let player_transform = player.single();
-    let new_transform = Transform::from_xyz(player_transform.translation.x, player_transform.translation.y, 0.01);
-    
+    let new_transform = Transform::from_xyz(screen_pos.x, screen_pos.y, 0.01)
+        .with_scale(Vec3::splat(tile_scale));
+
     // spawn a lose life animation here
     commands.spawn((
         SpriteBundle {
-            sprite: Sprite {custom_size: Some(Vec2::new(22.0, 22.0)), ..default()},
+            sprite: Sprite {custom_size: Some(Vec2::new(22.0, 22.0)), color: player_colour(player), ..default()},
             texture: asset_server.load("Pacman_LoseLife_SpriteSheet.png"),
             transform: new_transform,
             ..default()
@@ -639,9 +785,19 @@ fn handle_lose_life(
         //TextureAtlasIndex(animation_indicies.first),
         animation_indicies,
         AnimationTimer(Timer::from_seconds(0.3, TimerMode::Repeating)),
-        LoseLife(Timer::from_seconds(1.2, TimerMode::Once)),
-    ));
+    ))
+}
 
+fn despawn_player_caught_animations(
+    time: Res<Time>,
+    mut animations: Query<(Entity, &mut PlayerCaughtAnimation)>,
+    mut commands: Commands,
+) {
+    for (entity, mut timer) in animations.iter_mut() {
+        if timer.tick(time.delta()).finished() {
+            commands.entity(entity).despawn();
+        }
+    }
 }
 
 fn check_lose_life_animation(
@@ -649,6 +805,9 @@ fn check_lose_life_animation(
     mut lose_life: Query<&mut LoseLife>,
     mut lives_left: ResMut<LivesLeft>,
     mut game_state: ResMut<NextState<GameState>>,
+    players: Res<Players>,
+    simulation: Res<GameSimulation>,
+    mut commands: Commands,
     //mut ghosts: Query<&Transform, With<GhostEyes>>,
 ) {
     let mut timer = lose_life.single_mut();
@@ -658,7 +817,14 @@ fn check_lose_life_animation(
         // reduce lives left
         lives_left.0 -= 1;
 
-        if lives_left.0 == 0 {
+        if players.other_player_can_play() {
+            // over to the other player, their board is set up again as they left it
+            commands.insert_resource(SwapPlayers);
+            game_state.set(GameState::LevelSetup);
+        } else if lives_left.0 == 0 && simulation.human_ghost.is_some() {
+            commands.insert_resource(MatchWinner::Ghosts);
+            game_state.set(GameState::MatchOver);
+        } else if lives_left.0 == 0 {
             game_state.set(GameState::GameOver);
         } else {
             // move ghosts to start locations
@@ -667,26 +833,6 @@ fn check_lose_life_animation(
     }
 }
 
-/*
- * Get screen coords of the given col and row (gameboard position)
- */
-pub fn get_screen_coords(col_index: f32, row_index: f32) -> Vec2 {
-    Vec2 {
-        x: ((col_index * 15.0) - (SCREEN_WIDTH_PX / 2.0)) + 18.0,
-        y: ((((BOARD_HEIGHT as f32 - 1.0) - row_index) * 15.0) - (SCREEN_HEIGHT_PX / 2.0)) + 5.0
-    }
-}
-
-/*
- * Get the gameboard coordinates of the given screen position
- */
-pub fn get_game_board_coords(pos: Vec2) -> Vec2 {
-    Vec2 {
-        x: ((pos.x - 18.0) + (SCREEN_WIDTH_PX / 2.0)) / 15.0,
-        y: (BOARD_HEIGHT as f32 - 1.0) - (((pos.y - 5.0) + (SCREEN_HEIGHT_PX / 2.0)) / 15.0)
-    }
-}
-
 /*
  * Does a basic check of the collision of 2 rectangles
  * - Can fail to detect a collision if object2 is smaller than object1
@@ -729,13 +875,11 @@ pub fn get_new_position_alt(game_logic: &GameLogic, current_pos: Vec2, direction
     cell_to_check.y += direction.vertical as i32 as f32;
 
     // verify if cell to check is out of bounds
-    if cell_to_check.x >= 0.0 && cell_to_check.x < (BOARD_WIDTH as f32) &&
-       cell_to_check.y >= 0.0 && cell_to_check.y < (BOARD_HEIGHT as f32) {
+    if let Some(cell) = game_logic.get_block(cell_to_check.x as i32, cell_to_check.y as i32) {
         // cell coords are valid
-        let cell = game_logic.game_blocks[cell_to_check.y as usize][cell_to_check.x as usize];
 
         match cell.block_type {
-            BlockType::Wall => {
+            BlockType::Wall | BlockType::PenDoor => {
                 // check collision of entity with this cell
                 check_for_collision = true;
             },
@@ -765,7 +909,7 @@ pub fn get_new_position_alt(game_logic: &GameLogic, current_pos: Vec2, direction
         }
     }
 
-    if return_val.1 == false {
+    if !return_val.1 {
         // no collision has occurred, update the new position
         return_val.0 = new_pos;
     }
@@ -794,7 +938,7 @@ pub fn at_decision_point(position: Vec2, direction: Direction) -> bool {
         max_diff = 0.0;
     }
 
-    return diff >= min_diff && diff < max_diff;
+    diff >= min_diff && diff < max_diff
 }
 
 /* Get the available directions from the current position on the gameboard
@@ -806,9 +950,7 @@ pub fn get_available_directions(position: Vec2, direction: Direction, gamelogic:
     let block_pos = position.round();
 
     // check if block position is a valid gameboard position
-    if block_pos.x >= 0.0 && block_pos.x < BOARD_WIDTH as f32 &&
-       block_pos.y >= 0.0 && block_pos.y < BOARD_HEIGHT as f32
-    {
+    if gamelogic.get_block(block_pos.x as i32, block_pos.y as i32).is_some() {
         // check left, right up and down directions
         let directions: [Direction; 4] = 
             [Direction {vertical: Vertical::Zero, horizontal: Horizontal::Left},
@@ -817,16 +959,7 @@ pub fn get_available_directions(position: Vec2, direction: Direction, gamelogic:
              Direction {vertical: Vertical::Down, horizontal: Horizontal::Zero}];
 
         // calculate the opposite direction
-        let mut opposite_direction = direction;
-        opposite_direction.horizontal = 
-            if direction.horizontal == Horizontal::Left { Horizontal::Right }
-            else if direction.horizontal == Horizontal::Right { Horizontal::Left }
-            else { Horizontal::Zero };
-        
-        opposite_direction.vertical = 
-            if direction.vertical == Vertical::Up { Vertical::Down }
-            else if direction.vertical == Vertical::Down { Vertical::Up }
-            else { Vertical::Zero };
+        let opposite_direction = direction.opposite();
 
         for check_dir in &directions {
 
@@ -836,19 +969,72 @@ pub fn get_available_directions(position: Vec2, direction: Direction, gamelogic:
                 let check_pos = Vec2 {x: block_pos.x + check_dir.horizontal as i32 as f32, y: block_pos.y + check_dir.vertical as i32 as f32};
 
                 // check if check_pos is a valid gameboard position
-                if check_pos.x >= 0.0 && check_pos.x < BOARD_WIDTH as f32 &&
-                check_pos.y >= 0.0 && check_pos.y < BOARD_HEIGHT as f32
-                {
-                    if let BlockType::Wall = gamelogic.game_blocks[check_pos.y as usize][check_pos.x as usize].block_type {
+                if let Some(check_block) = gamelogic.get_block(check_pos.x as i32, check_pos.y as i32) {
+                    if let BlockType::Wall | BlockType::PenDoor = check_block.block_type {
                         // do nothing for wall
                     }
                     else {
-                        avail_dirs.push(check_dir.clone());
+                        avail_dirs.push(*check_dir);
                     }
                 }
             }
         }
     }
 
-    return avail_dirs;
-}
\ No newline at end of file
+    avail_dirs
+}
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::RunSystemOnce;
+
+    use super::*;
+    use crate::extralife::{ExtraLifePlugin, ExtraLifeRule};
+
+    // just enough of the game to set up the gameboard and hand out extra lives
+    fn game_app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, AssetPlugin::default(), ExtraLifePlugin))
+            .init_asset::<Maze>()
+            .init_resource::<Players>()
+            .init_resource::<GhostFrightenedBehaviour>()
+            .init_resource::<GameMode>()
+            .insert_resource(ExtraLifeRule::default())
+            .insert_resource(Settings::default())
+            .insert_resource(GhostComboPoints(Scoring::default().ghost_combo))
+            .insert_resource(GameSeed(1))
+            .insert_resource(MazeHandle(Handle::default()))
+            .insert_resource(Score(0))
+            .insert_resource(LivesLeft(0))
+            .insert_resource(Level(0));
+        app
+    }
+
+    fn score_points(app: &mut App, score: i32) {
+        app.world_mut().resource_mut::<Score>().0 = score;
+        app.update();
+    }
+
+    #[test]
+    fn extra_life_is_given_again_in_the_next_game() {
+        let mut app = game_app();
+        let starting_lives = Settings::default().starting_lives;
+
+        app.world_mut().run_system_once(setup_gameboard);
+        assert_eq!(app.world().resource::<LivesLeft>().0, starting_lives);
+
+        score_points(&mut app, ExtraLifeRule::default().first);
+        assert_eq!(app.world().resource::<LivesLeft>().0, starting_lives + 1);
+        assert_eq!(app.world().resource::<ExtraLivesAwarded>().0, 1);
+
+        // game over without making the leaderboard goes straight back to setting up the gameboard, the main menu is
+        // never seen
+        app.world_mut().resource_mut::<LivesLeft>().0 = 0;
+        score_points(&mut app, 0);
+        app.world_mut().run_system_once(setup_gameboard);
+        assert_eq!(app.world().resource::<LivesLeft>().0, starting_lives);
+        assert_eq!(app.world().resource::<ExtraLivesAwarded>().0, 0);
+
+        score_points(&mut app, ExtraLifeRule::default().first);
+        assert_eq!(app.world().resource::<LivesLeft>().0, starting_lives + 1);
+    }
+}
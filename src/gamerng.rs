@@ -0,0 +1,149 @@
+use bevy::prelude::*;
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+
+use crate::config::{config_value, CONFIG_FILE};
+
+/*
+ * All of the randomness in gameplay comes from the one seeded random number generator, so a game can be
+ * reproduced exactly by running it again with the same seed.
+ *
+ * The seed is taken from (in order):
+ *  the command line:   rust_pacman --seed 1234
+ *  the config file:    a line "seed: 1234" in config.txt
+ *  otherwise a random seed is picked, it is logged so the game can be reproduced later
+ *
+ * Replays only store the seed, so the numbers that come out for a seed must never change. The generator is named
+ * rather than rand's StdRng (which can change algorithm between versions), and the numbers are turned into bools and
+ * indices here rather than by rand's distributions for the same reason. Both work the way StdRng, gen_bool and
+ * gen_range did in rand 0.8, so replays recorded before this still play back.
+ */
+
+pub struct GameRng(ChaCha12Rng);
+
+/*
+ * The seed every game is played with
+ */
+#[derive(Resource)]
+pub struct GameSeed(pub u64);
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        GameRng(ChaCha12Rng::seed_from_u64(seed))
+    }
+
+    /*
+     * True with the given probability (0 to 1) - a number is used up every time, apart from when it is always true
+     */
+    pub fn gen_bool(&mut self, probability: f64) -> bool {
+        if probability >= 1.0 {
+            return true;
+        }
+
+        let threshold = (probability.max(0.0) * 2.0 * (1u64 << 63) as f64) as u64;
+        self.0.next_u64() < threshold
+    }
+
+    /*
+     * Random index into a list of the given length
+     * Multiplies a random 64 bit number by the length and takes the top half, trying again for the few numbers that
+     * would make some indices more likely than others
+     */
+    pub fn gen_index(&mut self, length: usize) -> usize {
+        assert!(length > 0, "gen_index needs a length of at least 1");
+
+        let range = length as u64;
+        let zone = (range << range.leading_zeros()).wrapping_sub(1);
+        loop {
+            let product = self.0.next_u64() as u128 * range as u128;
+            if product as u64 <= zone {
+                return (product >> 64) as usize;
+            }
+        }
+    }
+}
+
+fn seed_from_args() -> Option<u64> {
+    let mut args = std::env::args();
+    while let Some(arg) = args.next() {
+        let value = if arg == "--seed" {
+            args.next()
+        } else {
+            arg.strip_prefix("--seed=").map(str::to_string)
+        };
+
+        if let Some(value) = value {
+            match value.parse() {
+                Ok(seed) => return Some(seed),
+                Err(_) => warn!("Ignoring invalid seed on the command line: {}", value),
+            }
+        }
+    }
+    None
+}
+
+fn seed_from_config() -> Option<u64> {
+    let value = config_value("seed")?;
+
+    match value.parse() {
+        Ok(seed) => Some(seed),
+        Err(_) => {
+            warn!("Ignoring invalid seed in {}: {}", CONFIG_FILE, value);
+            None
+        },
+    }
+}
+
+pub struct GameRngPlugin;
+
+impl Plugin for GameRngPlugin {
+    fn build(&self, app: &mut App) {
+        let seed = seed_from_args()
+            .or_else(seed_from_config)
+            .unwrap_or_else(rand::random);
+        info!("Gameplay random seed: {}", seed);
+
+        app.insert_resource(GameSeed(seed));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // what rand 0.8's StdRng, gen_range(0..4) and gen_bool(0.5) gave for seed 1234 - if any of these change, every
+    // replay ever recorded stops playing back
+    const PINNED_VALUES: [u64; 4] = [2185187624241326233, 3129480666983675374, 11352470181048926755, 7002498567703764763];
+    const PINNED_INDICES: [usize; 8] = [0, 2, 0, 3, 0, 1, 2, 3];
+    const PINNED_BOOLS: [bool; 8] = [true, true, false, true, false, true, true, false];
+
+    #[test]
+    fn numbers_for_a_seed_never_change() {
+        let mut rng = GameRng::new(1234);
+        let values: Vec<u64> = (0..4).map(|_| rng.0.next_u64()).collect();
+        assert_eq!(values, PINNED_VALUES);
+
+        let mut rng = GameRng::new(1234);
+        let indices: Vec<usize> = (0..8).map(|_| rng.gen_index(4)).collect();
+        assert_eq!(indices, PINNED_INDICES);
+
+        let mut rng = GameRng::new(1234);
+        let bools: Vec<bool> = (0..8).map(|_| rng.gen_bool(0.5)).collect();
+        assert_eq!(bools, PINNED_BOOLS);
+    }
+
+    #[test]
+    fn gen_bool_always_and_never() {
+        let mut rng = GameRng::new(99);
+        assert!((0..100).all(|_| rng.gen_bool(1.0)));
+        assert!((0..100).all(|_| !rng.gen_bool(0.0)));
+    }
+
+    #[test]
+    fn gen_index_stays_in_range() {
+        let mut rng = GameRng::new(7);
+        for length in 1..20 {
+            assert!((0..100).all(|_| rng.gen_index(length) < length));
+        }
+    }
+}
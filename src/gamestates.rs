@@ -3,18 +3,56 @@ use bevy::prelude::*;
 #[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States)]
 pub enum GameState {
     #[default]
-    SplashScreen,
+    MainMenu,
     LevelSetup,
     GameStart,
     Gameplay,
     LoseLife,
     LevelComplete,
     GameOver,
+    MatchOver,
 }
 
 /*
-SplashScreen
-Game starts in SplashScreen state: waiting for the user to press "start" or space bar - when pressed go into LevelSetup state
+ * The kind of game to play, picked from the main menu
+ */
+#[derive(Resource, Clone, Copy, Default, Eq, PartialEq, Debug)]
+pub enum GameMode {
+    #[default]
+    OnePlayer,
+    // two players taking turns, swapping over whenever one loses a life
+    TwoPlayer,
+    // two players on the board at the same time
+    CoOp,
+    // one player as Pac-Man against a second player steering Blinky
+    Versus,
+}
+
+impl GameMode {
+    pub const ALL: [GameMode; 4] = [GameMode::OnePlayer, GameMode::TwoPlayer, GameMode::CoOp, GameMode::Versus];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            GameMode::OnePlayer => "1 Player",
+            GameMode::TwoPlayer => "2 Players",
+            GameMode::CoOp => "Co-op",
+            GameMode::Versus => "Versus",
+        }
+    }
+
+    /*
+     * The next (or previous) mode in the list, wrapping around at the ends
+     */
+    pub fn cycle(&self, forwards: bool) -> GameMode {
+        let index = GameMode::ALL.iter().position(|mode| mode == self).unwrap_or(0);
+        let count = GameMode::ALL.len();
+        GameMode::ALL[if forwards { (index + 1) % count } else { (index + count - 1) % count }]
+    }
+}
+
+/*
+MainMenu
+Game starts in MainMenu state: the player picks what to do from the main menu - starting a game goes into LevelSetup state
 
 LevelSetup
 Setup all the gameboard objects on screen then move to GameStart state
@@ -27,14 +65,20 @@ All normal game processes occur in Gameplay state
 
 LoseLife
 When a player and ghost collision is detected, the game goes into LoseLife state - the ghosts and player reset but the rest stays the same (gameboard, points tokens, score etc),
-if there are lives left, a few seconds pass before going back into the Gameplay state, otherwise go to GameOver state
+if there are lives left, a few seconds pass before going back into the Gameplay state, otherwise go to GameOver state.
+In a two player game it goes back into LevelSetup state for the other player's turn instead, if they have lives left.
+In co-op a player that is caught while the other is still going leaves the board without leaving Gameplay state, it is only once both
+have been caught that the game goes into LoseLife state
 
 LevelComplete
 When all points tokens are eaten by the player - the level is complete, the score stays the same and the level is increased,
-the complete gameboard is cleared and we go back into LevelSetup state (the level value should affect speed of both player and ghosts)
+the complete gameboard is cleared and we go back into LevelSetup state (the level value picks the speeds, timings and fruit from the level table in levels.rs)
 
 GameOver
-Show game over on screen, check if the player has a highscore for the scoreboard, if so get them to enter their initials, clear score and level values and go back to splash screen
+Show game over on screen, check if the player has a highscore for the scoreboard, if so get them to enter their initials, clear score and level values and go back to the main menu
+
+MatchOver
+A versus match goes here instead of LevelComplete or GameOver - show which side won, clear score and lives and go back to the main menu
 */
 
 pub fn despawn_screen<T: Component>(to_despawn: Query<Entity, With<T>>, mut commands: Commands) {
@@ -1,51 +1,78 @@
-use bevy::prelude::*;
+use std::time::Duration;
 
-use rand::prelude::*;
+use bevy::prelude::*;
 
-use crate::gamelogic::{at_decision_point, check_collision, get_available_directions, get_new_position_alt, GameLogic, Horizontal, OnGameplayScreen, Player, Vertical};
-use crate::gamestates::GameState;
-use crate::{AnimationIndicies, AnimationTimer, ConsecutiveKills, Score};
+use crate::gamelogic::{at_decision_point, get_available_directions, get_new_position_alt, GameLogic, Horizontal, Vertical};
+use crate::ghostwaves::GhostWaveMode;
+use crate::levels::LevelSettings;
+use crate::pathfinding::Pathfinder;
+use crate::gamerng::GameRng;
+use crate::targeting::{Ambush, DirectChase, Flank, Shy, TargetingContext, TargetingStrategy};
 use crate::gamelogic;
 use gamelogic::Direction;
-use gamelogic::get_screen_coords;
-use gamelogic::get_game_board_coords;
-
-pub struct GhostPlugin;
 
-impl Plugin for GhostPlugin {
-    fn build(&self, app: &mut App) {
-        app
-            .add_systems(OnEnter(GameState::LevelSetup), spawn_ghosts)
-            .add_systems(Update, (move_ghost, check_ghost_player_collision, update_weakened_timers).run_if(in_state(GameState::Gameplay)));
-    }
-}
+/*
+ * Ghost behaviour
+ *
+ * Everything about how the ghosts move and decide where to go, in gameboard coordinates. The ghosts are stepped along by
+ * the simulation and the sprites in ghostsprites just follow them around.
+ */
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum GhostPositionStatus {
     InPen,
     OutAndAbout,
     ReturningToPen,
 }
 
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum GhostActionsStatus {
     Idle, // (in pen)
     LeavingPen,
+    Scattering,
     SearchingForPlayer,
     Weakened,
     RunningToPen,
     GoingIntoPen,
 }
 
+/*
+ * How weakened ghosts decide where to go
+ */
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum FrightenedBehaviour {
+    // as in the arcade - turn around as soon as they are weakened, then pick a random way at every junction
+    #[default]
+    Wander,
+    // head for the point directly opposite the player
+    Flee,
+}
+
+impl FrightenedBehaviour {
+    pub fn name(&self) -> &'static str {
+        match self {
+            FrightenedBehaviour::Wander => "wander",
+            FrightenedBehaviour::Flee => "flee",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<FrightenedBehaviour> {
+        match name {
+            "wander" => Some(FrightenedBehaviour::Wander),
+            "flee" => Some(FrightenedBehaviour::Flee),
+            _ => None,
+        }
+    }
+}
+
 const GHOST_WEAKENED_SPEED: f32 = 3.0;
 const GHOST_RUNNING_HOME: f32 = 6.0;
 
-#[derive(Component)]
 pub struct Ghost {
     //pub lifetime: Timer,
+    pub position: Vec2,
     pub direction_of_travel: Direction,
 
-    pub body_entity: Entity,
-    pub eyes_entity: Entity,
-
     pub speed: f32,
 
     pub position_status: GhostPositionStatus,
@@ -57,113 +84,90 @@ pub struct Ghost {
 
     pub name: String,
 
-    pub base_colour: Color,
-
     pub last_decision_point: Vec2,
-}
 
-#[derive(Component)]
-pub struct GhostBody {}
+    // where the ghost aims for while it is searching for the player
+    pub targeting: Box<dyn TargetingStrategy>,
 
-#[derive(Component)]
-pub struct GhostEyes {}
+    // where the ghost heads for while it is scattering
+    pub scatter_corner: Vec2,
 
-pub fn spawn_ghosts(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
-) {
-    // spawn our 4 ghosts
+    // steered by a player instead of deciding for itself at junctions, going the last way they pressed if it can
+    pub human_controlled: bool,
+    pub human_direction: Direction,
+}
+
+/*
+ * Everything outside of the ghost itself that it needs to know about to move
+ */
+pub struct GhostContext<'a> {
+    pub game_logic: &'a GameLogic,
+    pub player_pos: Vec2,
+    pub player_direction: Direction,
+    // where all the ghosts were before any of them moved, some ghosts target based on where the others are
+    pub ghost_positions: &'a [(String, Vec2)],
+    pub wave_mode: GhostWaveMode,
+    pub frightened_behaviour: FrightenedBehaviour,
+}
 
+/*
+ * The status a ghost that is out and about (and not weakened) should have in the given wave mode
+ */
+pub fn roaming_status(mode: GhostWaveMode) -> GhostActionsStatus {
+    match mode {
+        GhostWaveMode::Scatter => GhostActionsStatus::Scattering,
+        GhostWaveMode::Chase => GhostActionsStatus::SearchingForPlayer,
+    }
+}
+
+/*
+ * Create our 4 ghosts sat in the pen ready to start
+ */
+pub fn new_ghosts(game_logic: &GameLogic, settings: &LevelSettings) -> Vec<Ghost> {
     // ghost details holds the individual data for each of the ghosts
     struct GhostDetails {
         name: String,
-        speed: f32,
-        transform: Transform,
-        colour: Color,
+        // the ghosts are very slightly different speeds so they don't bunch up on top of each other
+        speed_offset: f32,
+        pen_offset: Vec2, // start position relative to the centre of the pen
         time_in_pen: f32,
+        targeting: Box<dyn TargetingStrategy>,
+        scatter_corner: Vec2,
     }
 
+    let corners = game_logic.scatter_corners;
+
     let ghost_details: [GhostDetails; 4] = [
         // red
-        GhostDetails { name: String::from("Blinky"),    speed: 4.00, transform: Transform::from_xyz(-20.0, 5.0, 0.01101), colour: Color::srgb (1.0, 0.0, 0.0), time_in_pen: 1.0 },
+        GhostDetails { name: String::from("Blinky"),    speed_offset: 0.0, pen_offset: Vec2::new(-1.33, 0.0), time_in_pen: settings.pen_release[0],
+            targeting: Box::new(DirectChase), scatter_corner: corners.blinky },
         // cyan
-        GhostDetails { name: String::from("Inky"),   speed: 4.01, transform: Transform::from_xyz(0.0, 10.0, 0.01102),  colour: Color::srgb (0.0, 1.0, 1.0), time_in_pen: 5.0 },
+        GhostDetails { name: String::from("Inky"),   speed_offset: 0.01, pen_offset: Vec2::new(0.0, -0.33), time_in_pen: settings.pen_release[1],
+            targeting: Box::new(Flank { partner: String::from("Blinky"), tiles_ahead: 2.0 }), scatter_corner: corners.inky },
         // pink
-        GhostDetails { name: String::from("Pinky"),   speed: 3.99, transform: Transform::from_xyz(20.0, 0.0, 0.01103),  colour: Color::srgb (1.0, 0.0, 1.0), time_in_pen: 9.0 },
+        GhostDetails { name: String::from("Pinky"),   speed_offset: -0.01, pen_offset: Vec2::new(1.33, 0.33), time_in_pen: settings.pen_release[2],
+            targeting: Box::new(Ambush { tiles_ahead: 4.0 }), scatter_corner: corners.pinky },
         // yellow
-        GhostDetails { name: String::from("Clyde"), speed: 3.98, transform: Transform::from_xyz(40.0, 15.0, 0.01104), colour: Color::srgb (1.0, 1.0, 0.0), time_in_pen: 13.0 }
+        GhostDetails { name: String::from("Clyde"), speed_offset: -0.02, pen_offset: Vec2::new(2.67, -0.67), time_in_pen: settings.pen_release[3],
+            // clyde runs off to his corner when he gets scared
+            targeting: Box::new(Shy { retreat_distance: 8.0, retreat_pos: corners.clyde }), scatter_corner: corners.clyde }
     ];
 
-    for ghost_detail in ghost_details {
-
-        let mut eyes_transform = ghost_detail.transform.clone();
-        eyes_transform.translation.z += 0.00001;
-
-        let ghost_size = UVec2::new(22, 24);
-        let ghost_anim_indicies = AnimationIndicies {first: 0, last: 4};
-
-        let eyes_indicies = AnimationIndicies {first: 0, last: 4};
-
-        let ghost = Ghost {
-            name: ghost_detail.name,
-            direction_of_travel: Direction {vertical: Vertical::Zero, horizontal: Horizontal::Left},
-            speed: ghost_detail.speed,
-            body_entity: commands.spawn((
-                SpriteBundle {
-                    sprite: Sprite {
-                        color: ghost_detail.colour,
-                        ..default()
-                    },
-                    transform: ghost_detail.transform,
-                    texture: asset_server.load("GhostBody_SpriteSheet.png"),
-                    ..default()
-                },
-                TextureAtlas {
-                    layout: texture_atlases.add(TextureAtlasLayout::from_grid(
-                    ghost_size,
-                    1,
-                    5,
-                    None, None)),
-                    index: ghost_anim_indicies.first,
-                },
-                ghost_anim_indicies,
-                AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating)),
-                GhostBody {},
-                OnGameplayScreen,
-            )).id(),
-
-            eyes_entity: commands.spawn((
-                SpriteBundle {
-                    transform: eyes_transform,
-                    texture: asset_server.load("GhostEyes_SpriteSheet.png"),
-                    ..default()
-                },
-                TextureAtlas {
-                    layout: texture_atlases.add(TextureAtlasLayout::from_grid(
-                        ghost_size,
-                        1,
-                        5,
-                        None, None)),
-                    index: eyes_indicies.first,
-                },
-                eyes_indicies,
-                GhostEyes {},
-                OnGameplayScreen,
-
-            )).id(),
-
-            position_status: GhostPositionStatus::InPen, // all ghosts start in the pen
-            actions_status: GhostActionsStatus::Idle,
-            time_in_pen: Timer::from_seconds(ghost_detail.time_in_pen, TimerMode::Once),
-            time_weakened: None,
-            last_decision_point: Vec2 {x: 0.0, y: 0.0},
-            base_colour: ghost_detail.colour,
-        };
-        
-        commands.spawn((ghost, OnGameplayScreen));
-
-    }
+    ghost_details.into_iter().map(|ghost_detail| Ghost {
+        name: ghost_detail.name,
+        position: game_logic.pen_centre + ghost_detail.pen_offset,
+        direction_of_travel: Direction {vertical: Vertical::Zero, horizontal: Horizontal::Left},
+        speed: settings.ghost_speed + ghost_detail.speed_offset,
+        position_status: GhostPositionStatus::InPen, // all ghosts start in the pen
+        actions_status: GhostActionsStatus::Idle,
+        time_in_pen: Timer::from_seconds(ghost_detail.time_in_pen, TimerMode::Once),
+        time_weakened: None,
+        last_decision_point: Vec2 {x: 0.0, y: 0.0},
+        targeting: ghost_detail.targeting,
+        scatter_corner: ghost_detail.scatter_corner,
+        human_controlled: false,
+        human_direction: Direction::ZERO,
+    }).collect()
 
     // test suite for ghost functions
     /*let mut direction_test: Vec<Direction> = Vec::new();
@@ -171,367 +175,300 @@ pub fn spawn_ghosts(
     direction_test.push(Direction {vertical: Vertical::Down, horizontal: Horizontal::Zero});
     direction_test.push(Direction {vertical: Vertical::Zero, horizontal: Horizontal::Left});
     direction_test.push(Direction {vertical: Vertical::Zero, horizontal: Horizontal::Right});
-     
+
     assert_eq!((false, 0), find_direction_match(&direction_test, &Direction {vertical: Vertical::Up, horizontal: Horizontal::Zero}));
     assert_eq!((false, 3), find_direction_match(&direction_test, &Direction {vertical: Vertical::Zero, horizontal: Horizontal::Right}));*/
 }
 
+impl Ghost {
+    /*
+     * Move the ghost on by the given amount of time
+     */
+    pub fn update(&mut self, context: &GhostContext, pathfinder: &mut Pathfinder, rng: &mut GameRng, delta: Duration) {
+        let game_logic = context.game_logic;
 
-fn move_ghost(
-    mut ghosts: Query<&mut Ghost>,
-    mut ghost_eyes_transforms: Query<(&mut Transform, &AnimationIndicies, &mut TextureAtlas), (With<GhostEyes>, Without<GhostBody>)>,
-    mut ghost_body_transforms: Query<(&mut Transform, &mut Sprite), (With<GhostBody>, Without<GhostEyes>)>,
-    player_transform: Query<&Transform, (With<Player>, Without<GhostBody>, Without<GhostEyes>)>,
-    game_logic: Query<&GameLogic>,
-    time: Res<Time>,
-) {
-    let player_transform = player_transform.single();
-    let player_game_pos = get_game_board_coords(Vec2 {x: player_transform.translation.x, y: player_transform.translation.y});
-
-    for mut ghost in &mut ghosts {
-        if let Ok((mut eye_transform, indices, mut atlas)) = ghost_eyes_transforms.get_mut(ghost.eyes_entity) {
-            if let Ok((mut body_transform, mut ghost_sprite)) = ghost_body_transforms.get_mut(ghost.body_entity) {
-                
-                let mut new_pos = get_game_board_coords(Vec2 {x: eye_transform.translation.x, y: eye_transform.translation.y} );
-
-                let pen_movement = 2.0 * time.delta_seconds();
-                
-                // distance calculated as pythagoras
-                let distance_from_player = ((player_game_pos.x - new_pos.x).abs().powi(2) + (player_game_pos.y - new_pos.y).abs().powi(2)).sqrt();
-
-                const PEN_EXIT: Vec2 = Vec2 {x: 12.5, y:10.0};
-
-                match ghost.actions_status {
-                    GhostActionsStatus::Idle => {
-                        new_pos.x += pen_movement * ghost.direction_of_travel.horizontal as i32 as f32;
-                        new_pos.y += pen_movement * ghost.direction_of_travel.vertical as i32 as f32;
-
-                        if new_pos.x > 15.0 {
-                            ghost.direction_of_travel.horizontal = Horizontal::Left;
-                        } else if new_pos.x < 10.0 {
-                            ghost.direction_of_travel.horizontal = Horizontal::Right;
-                        }
-                        
-                        ghost.time_in_pen.tick(time.delta());
-                        if ghost.time_in_pen.just_finished() {
-                            ghost.actions_status = GhostActionsStatus::LeavingPen;
-                        }
-                    },
-                    GhostActionsStatus::LeavingPen => {
-                        // check if we are in line with the pen exit x position
-                        if (new_pos.x - PEN_EXIT.x).abs() < 0.1 {
-
-                            new_pos.x = PEN_EXIT.x;
-                            
-                            // move towards the pen exit y position
-                            let y_diff = new_pos.y - PEN_EXIT.y;
-                            new_pos.y -= if y_diff > 0.0 {
-                                ghost.direction_of_travel.vertical = Vertical::Up;
-
-                                pen_movement.min(y_diff) // we are below the exit pos
-                            } else {
-                                ghost.direction_of_travel.vertical = Vertical::Down;
-                                (-pen_movement).max(y_diff) // we are above the exit pos
-                            };
-                            
-                            ghost.direction_of_travel.horizontal = Horizontal::Zero;
-
-                            // check if we are now in line with the pen exit y position
-                            if (new_pos.y - PEN_EXIT.y).abs() < 0.01 {
-                                new_pos.y = PEN_EXIT.y;
-                                // we are out of the pen
-                                ghost.actions_status = GhostActionsStatus::SearchingForPlayer;
-                                ghost.position_status = GhostPositionStatus::OutAndAbout;
-                                
-                                ghost.direction_of_travel.vertical = Vertical::Zero;
-                                
-                                if random() { 
-                                    ghost.direction_of_travel.horizontal = Horizontal::Left;
-                                } else {
-                                    ghost.direction_of_travel.horizontal = Horizontal::Right;
-                                }
-                            }
-                        } else {
-                            // move towards pen exit x position
-                            let x_diff = new_pos.x - PEN_EXIT.x;
-
-                            new_pos.x -= if x_diff > 0.0 {
-                                ghost.direction_of_travel.horizontal = Horizontal::Left;
-                                pen_movement.min(x_diff)
-                            } else {
-                                ghost.direction_of_travel.horizontal = Horizontal::Right;
-                                (-pen_movement).max(x_diff)
-                            };
-
-                            ghost.direction_of_travel.vertical = Vertical::Zero;
-                        }
+        let mut new_pos = self.position;
 
-                    },
-                    GhostActionsStatus::SearchingForPlayer => {
-                        let movement = ghost.speed * time.delta_seconds();
-                        
-                        // as distance from player gets bigger, chance to do random movements decreases until some threshold
-                        let chance_of_random_action = (distance_from_player / 30.0).min(0.9); // based on distance from player (or 0.9 if distance is too far)
-
-                        new_pos = ghost_decisions(
-                            movement, 
-                            game_logic.single(),
-                            new_pos, 
-                            chance_of_random_action, 
-                            &mut ghost,
-                            player_game_pos
-                        );
-
-                    },
-                    GhostActionsStatus::Weakened => {
-                        // running away from player - ghost will aim for a position that is directly opposite from the position of the player
-                        let movement = GHOST_WEAKENED_SPEED * time.delta_seconds();
-
-                        // this is a position directly opposite the player position
-                        let ghost_pos_aim = Vec2::new((2.0 * new_pos.x) - player_game_pos.x, (2.0 * new_pos.y) - player_game_pos.y);
-
-                        new_pos = ghost_decisions(
-                            movement,
-                            game_logic.single(),
-                            new_pos,
-                            0.2,
-                            &mut ghost,
-                            ghost_pos_aim
-                        );
-
-                    },
-                    GhostActionsStatus::RunningToPen => {
-                        let movement = GHOST_RUNNING_HOME * time.delta_seconds();
-
-                        new_pos = ghost_decisions(
-                            movement,
-                            game_logic.single(),
-                            new_pos,
-                            0.0,
-                            &mut ghost,
-                            PEN_EXIT
-                        );
-
-                        // return the ghost to the pen if the ghost has reached the PEN_EXIT
-                        let diff = (new_pos - PEN_EXIT).abs();
-                        if diff.x < 0.3 && diff.y < 0.3 {
-                            ghost.actions_status = GhostActionsStatus::GoingIntoPen;
-                            new_pos = PEN_EXIT;
-                        }
-                    },
-                    GhostActionsStatus::GoingIntoPen => {
-                        // move back into the pen - then go back to normal and idle
-
-                        const PEN_CENTER: Vec2 = Vec2 { x: 12.5, y: 13.0 };
-
-                        new_pos.x = PEN_CENTER.x;
-                        ghost.direction_of_travel.vertical = Vertical::Down;
-                        ghost.direction_of_travel.horizontal = Horizontal::Zero;
-
-                        let y_diff = PEN_CENTER.y - new_pos.y;
-
-                        new_pos.y += pen_movement.min(y_diff); // move either the pen movement or the y diff whichever is smaller
-
-                        
-                        // check if we are now in line with the pen exit y position
-                        if (new_pos.y - PEN_CENTER.y).abs() < 0.01 {
-                            new_pos.y = PEN_CENTER.y;
-                            // we are now in the center of the pen
-                            ghost.actions_status = GhostActionsStatus::Idle;
-                            ghost.position_status = GhostPositionStatus::InPen;
-                            
-                            ghost.direction_of_travel.vertical = Vertical::Zero;
-
-                            // regenerate the ghost body
-                            ghost_sprite.color = ghost.base_colour;
-                            ghost.time_in_pen = Timer::from_seconds(5.0, TimerMode::Once);
-                            
-                            if random() { 
-                                ghost.direction_of_travel.horizontal = Horizontal::Left;
-                            } else {
-                                ghost.direction_of_travel.horizontal = Horizontal::Right;
-                            }
+        let pen_movement = 2.0 * delta.as_secs_f32();
+
+        let pen_exit = game_logic.pen_exit;
+        let pen_centre = game_logic.pen_centre;
+
+        match self.actions_status {
+            GhostActionsStatus::Idle => {
+                new_pos.x += pen_movement * self.direction_of_travel.horizontal as i32 as f32;
+                new_pos.y += pen_movement * self.direction_of_travel.vertical as i32 as f32;
+
+                if new_pos.x > pen_centre.x + 2.5 {
+                    self.direction_of_travel.horizontal = Horizontal::Left;
+                } else if new_pos.x < pen_centre.x - 2.5 {
+                    self.direction_of_travel.horizontal = Horizontal::Right;
+                }
+
+                self.time_in_pen.tick(delta);
+                if self.time_in_pen.just_finished() {
+                    self.actions_status = GhostActionsStatus::LeavingPen;
+                }
+            },
+            GhostActionsStatus::LeavingPen => {
+                // check if we are in line with the pen exit x position
+                if (new_pos.x - pen_exit.x).abs() < 0.1 {
+
+                    new_pos.x = pen_exit.x;
+
+                    // move towards the pen exit y position
+                    let y_diff = new_pos.y - pen_exit.y;
+                    new_pos.y -= if y_diff > 0.0 {
+                        self.direction_of_travel.vertical = Vertical::Up;
+
+                        pen_movement.min(y_diff) // we are below the exit pos
+                    } else {
+                        self.direction_of_travel.vertical = Vertical::Down;
+                        (-pen_movement).max(y_diff) // we are above the exit pos
+                    };
+
+                    self.direction_of_travel.horizontal = Horizontal::Zero;
+
+                    // check if we are now in line with the pen exit y position
+                    if (new_pos.y - pen_exit.y).abs() < 0.01 {
+                        new_pos.y = pen_exit.y;
+                        // we are out of the pen
+                        debug!("{} has left the pen", self.name);
+                        self.actions_status = roaming_status(context.wave_mode);
+                        self.position_status = GhostPositionStatus::OutAndAbout;
+
+                        self.direction_of_travel.vertical = Vertical::Zero;
+
+                        if rng.gen_bool(0.5) {
+                            self.direction_of_travel.horizontal = Horizontal::Left;
+                        } else {
+                            self.direction_of_travel.horizontal = Horizontal::Right;
                         }
                     }
+                } else {
+                    // move towards pen exit x position
+                    let x_diff = new_pos.x - pen_exit.x;
+
+                    new_pos.x -= if x_diff > 0.0 {
+                        self.direction_of_travel.horizontal = Horizontal::Left;
+                        pen_movement.min(x_diff)
+                    } else {
+                        self.direction_of_travel.horizontal = Horizontal::Right;
+                        (-pen_movement).max(x_diff)
+                    };
+
+                    self.direction_of_travel.vertical = Vertical::Zero;
                 }
 
+            },
+            GhostActionsStatus::Scattering => {
+                let movement = self.speed * delta.as_secs_f32();
+                let scatter_corner = self.scatter_corner;
+
+                new_pos = self.ghost_decisions(
+                    movement,
+                    game_logic,
+                    pathfinder,
+                    rng,
+                    new_pos,
+                    0.0,
+                    scatter_corner
+                );
+            },
+            GhostActionsStatus::SearchingForPlayer => {
+                let movement = self.speed * delta.as_secs_f32();
+
+                // each ghost has its own idea of where to go to catch the player
+                let target_pos = self.targeting.target(&TargetingContext {
+                    ghost_pos: new_pos,
+                    player_pos: context.player_pos,
+                    player_direction: context.player_direction,
+                    ghost_positions: context.ghost_positions,
+                });
+
+                // no random turns while chasing - they used to turn at random more often the further they were from
+                // the player, which hid the differences between the targeting strategies
+                new_pos = self.ghost_decisions(
+                    movement,
+                    game_logic,
+                    pathfinder,
+                    rng,
+                    new_pos,
+                    0.0,
+                    target_pos
+                );
 
+            },
+            GhostActionsStatus::Weakened => {
+                let movement = GHOST_WEAKENED_SPEED * delta.as_secs_f32();
+
+                let (chance_of_random, ghost_pos_aim) = match context.frightened_behaviour {
+                    // every decision is a random one, so the aim doesn't matter
+                    FrightenedBehaviour::Wander => (1.0, new_pos),
+                    // running away from player - ghost will aim for a position that is directly opposite from the position of the player
+                    FrightenedBehaviour::Flee => (0.2, Vec2::new((2.0 * new_pos.x) - context.player_pos.x, (2.0 * new_pos.y) - context.player_pos.y)),
+                };
+
+                new_pos = self.ghost_decisions(
+                    movement,
+                    game_logic,
+                    pathfinder,
+                    rng,
+                    new_pos,
+                    chance_of_random,
+                    ghost_pos_aim
+                );
 
-                // update eyes direction
-                let sprite_index =
-                    
-                    if ghost.direction_of_travel.horizontal ==      Horizontal::Right { 0 } // right
-                    else if ghost.direction_of_travel.horizontal == Horizontal::Left  { 1 } // left
-                    else if ghost.direction_of_travel.vertical ==   Vertical::Up      { 2 } // up
-                    else if ghost.direction_of_travel.vertical ==   Vertical::Down    { 3 } // down
-                    else { 4 }; // no direction
-                
-                // if sprite index is valid, update the sprite index
-                if sprite_index >= indices.first && sprite_index <= indices.last {
-                    atlas.index = sprite_index;
+            },
+            GhostActionsStatus::RunningToPen => {
+                let movement = GHOST_RUNNING_HOME * delta.as_secs_f32();
+
+                new_pos = self.ghost_decisions(
+                    movement,
+                    game_logic,
+                    pathfinder,
+                    rng,
+                    new_pos,
+                    0.0,
+                    pen_exit
+                );
+
+                // return the ghost to the pen if the ghost has reached the pen_exit
+                let diff = (new_pos - pen_exit).abs();
+                if diff.x < 0.3 && diff.y < 0.3 {
+                    self.actions_status = GhostActionsStatus::GoingIntoPen;
+                    new_pos = pen_exit;
                 }
+            },
+            GhostActionsStatus::GoingIntoPen => {
+                // move back into the pen - then go back to normal and idle
+
+                new_pos.x = pen_centre.x;
+                self.direction_of_travel.vertical = Vertical::Down;
+                self.direction_of_travel.horizontal = Horizontal::Zero;
 
-                // update transforms for both eyes and body
-                let screen_pos = get_screen_coords(new_pos.x, new_pos.y);
-                eye_transform.translation.x = screen_pos.x;
-                eye_transform.translation.y = screen_pos.y;
+                let y_diff = pen_centre.y - new_pos.y;
 
-                body_transform.translation.x = eye_transform.translation.x;
-                body_transform.translation.y = eye_transform.translation.y;
+                new_pos.y += pen_movement.min(y_diff); // move either the pen movement or the y diff whichever is smaller
+
+
+                // check if we are now in line with the pen exit y position
+                if (new_pos.y - pen_centre.y).abs() < 0.01 {
+                    new_pos.y = pen_centre.y;
+                    // we are now in the center of the pen, the ghost body regenerates
+                    self.actions_status = GhostActionsStatus::Idle;
+                    self.position_status = GhostPositionStatus::InPen;
+
+                    self.direction_of_travel.vertical = Vertical::Zero;
+
+                    self.time_in_pen = Timer::from_seconds(5.0, TimerMode::Once);
+
+                    if rng.gen_bool(0.5) {
+                        self.direction_of_travel.horizontal = Horizontal::Left;
+                    } else {
+                        self.direction_of_travel.horizontal = Horizontal::Right;
+                    }
+                }
             }
         }
+
+        self.position = new_pos;
     }
-}
 
-fn ghost_decisions(
-    movement_amount: f32,
-    game_logic: &GameLogic,
-    mut current_pos: Vec2,
-    chance_of_random: f32,
-    ghost: &mut Ghost,
-    ghost_pos_aim: Vec2,
-) -> Vec2 {
-    // check if we are at an intersection to make a decision
-    // otherwise just continue in the direction we were going before (no need to change anything)
-
-    let rounded_new_pos = current_pos.round();
-    
-    // for each "decision point" only handle them once (each ghost will record which one it has handled most recently)
-    if (rounded_new_pos.x as i32 != ghost.last_decision_point.x as i32 || 
-        rounded_new_pos.y as i32 != ghost.last_decision_point.y as i32) &&
-        at_decision_point(current_pos, ghost.direction_of_travel) {
+    #[allow(clippy::too_many_arguments)]
+    fn ghost_decisions(
+        &mut self,
+        movement_amount: f32,
+        game_logic: &GameLogic,
+        pathfinder: &mut Pathfinder,
+        rng: &mut GameRng,
+        mut current_pos: Vec2,
+        chance_of_random: f32,
+        ghost_pos_aim: Vec2,
+    ) -> Vec2 {
+        // check if we are at an intersection to make a decision
+        // otherwise just continue in the direction we were going before (no need to change anything)
+
+        let rounded_new_pos = current_pos.round();
+
+        // for each "decision point" only handle them once (each ghost will record which one it has handled most recently)
+        if (rounded_new_pos.x as i32 != self.last_decision_point.x as i32 ||
+            rounded_new_pos.y as i32 != self.last_decision_point.y as i32) &&
+            at_decision_point(current_pos, self.direction_of_travel) {
+
+            let available_directions = get_available_directions(current_pos, self.direction_of_travel, game_logic);
+
+            // make sure there are directions in the list
+            if !available_directions.is_empty() {
+
+                let mut decision = 0;
+
+                // a player steering the ghost picks the way at junctions while it is roaming, and it carries straight on
+                // if they haven't pressed a way it can go - when frightened it moves like the other ghosts do and the
+                // eyes find their own way back to the pen
+                let steerable = matches!(self.actions_status, GhostActionsStatus::Scattering | GhostActionsStatus::SearchingForPlayer);
+                let human_decision = if self.human_controlled && steerable {
+                    [self.human_direction, self.direction_of_travel].iter()
+                        .map(|direction| find_direction_match(&available_directions, direction))
+                        .find(|(direction_not_found, _)| !direction_not_found)
+                        .map(|(_, index)| index)
+                } else {
+                    None
+                };
 
-        let available_directions = get_available_directions(current_pos, ghost.direction_of_travel, game_logic);
+                if let Some(human_decision) = human_decision {
+                    decision = human_decision;
+                } else if available_directions.len() != 1 {
+                    // if there is more than 1 available direction
 
-        // make sure there are directions in the list
-        if available_directions.is_empty() == false {
-            
-            let mut decision = 0;
+                    //let chance_of_random_action = (distance_from_player / 30.0).min(0.9); // based on distance from player (or 0.9 if distance is too far)
 
-            // if there is more than 1 available direction
-            if available_directions.len() != 1 {
+                    if rng.gen_bool(chance_of_random as f64) {
 
-                //let chance_of_random_action = (distance_from_player / 30.0).min(0.9); // based on distance from player (or 0.9 if distance is too far)
-                
-                if thread_rng().gen_bool(chance_of_random as f64) {
-                    
-                    // choose a random direction
-                    decision = thread_rng().gen_range(0..available_directions.len());
+                        // choose a random direction
+                        decision = rng.gen_index(available_directions.len());
 
-                } else {
+                    } else {
 
-                    decision = ghost_to_position(ghost_pos_aim, current_pos, &available_directions);
+                        // take the shortest route to the aim position, falling back to just heading in its general direction
+                        // if it can't be reached
+                        decision = pathfinder.best_direction(game_logic, current_pos, ghost_pos_aim, &available_directions)
+                            .unwrap_or_else(|| ghost_to_position(ghost_pos_aim, current_pos, &available_directions, rng));
+                    }
                 }
-            }
 
-            // change direction to the one in the decision
-            let has_changed = available_directions[decision].vertical != ghost.direction_of_travel.vertical ||
-                                    available_directions[decision].horizontal != ghost.direction_of_travel.horizontal;
-
-            ghost.direction_of_travel = available_directions[decision];
-
-            if has_changed {
-                if let Horizontal::Zero = ghost.direction_of_travel.horizontal {
-                    // we are now moving vertically
-                    // snap our position to the rounded x value
-                    current_pos.x = current_pos.x.round();
-                } else if let Vertical::Zero = ghost.direction_of_travel.vertical {
-                    // we are now moving horizontally
-                    // snap our position to the rounded y value
-                    current_pos.y = current_pos.y.round();
-                }
-            }
-            
-            ghost.last_decision_point = rounded_new_pos;
-        }
-    }
-    
-    // try to move in the current direction of travel
-    let new_pos = get_new_position_alt(game_logic, current_pos, ghost.direction_of_travel, movement_amount).0;
+                // change direction to the one in the decision
+                let has_changed = available_directions[decision].vertical != self.direction_of_travel.vertical ||
+                                        available_directions[decision].horizontal != self.direction_of_travel.horizontal;
 
-    return new_pos;
-}
+                self.direction_of_travel = available_directions[decision];
 
-fn check_ghost_player_collision(
-    mut ghost_bodys: Query<(&Transform, &mut Sprite), (With<GhostBody>, Without<Player>)>,
-    player_transform: Query<&Transform, (With<Player>, Without<GhostBody>)>,
-    mut ghosts: Query<&mut Ghost>,
-    mut game_state: ResMut<NextState<GameState>>,
-    mut score: ResMut<Score>,
-    mut consecutive_kills: ResMut<ConsecutiveKills>,
-) {
-    let player_transform = player_transform.single();
-    let player_rect = Rect::from_center_size(Vec2 {x: player_transform.translation.x, y: player_transform.translation.y}, Vec2 {x: 21.0, y: 21.0});
-
-    //let player_pos = Vec2 {x: player_tranform.translation.x, y: player_tranform.translation.y};
-
-    for mut ghost in &mut ghosts {
-        // get the transform associated with this ghost
-        if let Ok((ghost_transform, mut ghost_sprite)) = ghost_bodys.get_mut(ghost.body_entity) {
-            if check_collision(
-                player_rect,
-                Rect::from_center_size(
-                    Vec2 {x: ghost_transform.translation.x, y: ghost_transform.translation.y},
-                    Vec2 {x: 21.0, y: 21.0}
-                )
-            ) {
-                // collision detected
-                match ghost.actions_status {
-                    GhostActionsStatus::Weakened => {
-                        ghost.actions_status = GhostActionsStatus::RunningToPen;
-                        ghost.position_status = GhostPositionStatus::ReturningToPen;
-                        // set ghost body colour to be transparent
-                        ghost_sprite.color = Color::srgba(0.0, 0.0, 0.0, 0.0);
-
-                        ghost.time_weakened = None;
-
-                        // considered putting a cap on this but normal operation should really only let 
-                        //  the player get to consecutive kills equal to number of ghosts - it could cause a funny, non-game-breaking glitch otherwise
-                        // TLDR; no cap intentionally
-                        consecutive_kills.0 += 1;
-                        score.0 += 100 * consecutive_kills.0;
-                    },
-                    GhostActionsStatus::RunningToPen => {
-                        // ghost is running to pen - do nothing
-                        // if we wanted we could do something here - stall the ghost while it runs home? etc.
-                    },
-                    GhostActionsStatus::SearchingForPlayer => {
-                        // all other times - touching a ghost means lose a life
-                        game_state.set(GameState::LoseLife);
-                    },
-                    _ => {
+                // the way the player pressed has been taken
+                if self.direction_of_travel.vertical == self.human_direction.vertical &&
+                    self.direction_of_travel.horizontal == self.human_direction.horizontal {
+                    self.human_direction = Direction::ZERO;
+                }
 
+                if has_changed {
+                    if let Horizontal::Zero = self.direction_of_travel.horizontal {
+                        // we are now moving vertically
+                        // snap our position to the rounded x value
+                        current_pos.x = current_pos.x.round();
+                    } else if let Vertical::Zero = self.direction_of_travel.vertical {
+                        // we are now moving horizontally
+                        // snap our position to the rounded y value
+                        current_pos.y = current_pos.y.round();
                     }
                 }
+
+                self.last_decision_point = rounded_new_pos;
             }
         }
-    }
-}
 
-fn update_weakened_timers(
-    mut ghosts: Query<&mut Ghost>,
-    mut ghost_bodys: Query<&mut Sprite, (With<GhostBody>, Without<Player>)>,
-    time: Res<Time>,
-) {
-    for mut ghost in ghosts.iter_mut() {
-
-        match &mut ghost.time_weakened {
-            Some(timer) => {
-                timer.tick(time.delta());
-            
-                if timer.finished() {
-                    // change this ghost back to normal
-                    ghost.actions_status = GhostActionsStatus::SearchingForPlayer;
-
-                    if let Ok(mut body_sprite) = ghost_bodys.get_mut(ghost.body_entity) {
-                        body_sprite.color = ghost.base_colour;
-                    }
+        // try to move in the current direction of travel
 
-                    ghost.time_weakened = None;
-                }
-            },
-            None => {},
-        }
+
+        get_new_position_alt(game_logic, current_pos, self.direction_of_travel, movement_amount).0
     }
 }
 
@@ -551,11 +488,11 @@ fn find_direction_match(available_directions: &Vec<Direction>, search: &Directio
 
         decision_index += 1;
     }
-    
+
     (decision_index == available_directions.len(), found_index)
 }
 
-fn ghost_to_position(position_aim: Vec2, current_position: Vec2, available_directions: &Vec<Direction>) -> usize {
+fn ghost_to_position(position_aim: Vec2, current_position: Vec2, available_directions: &Vec<Direction>, rng: &mut GameRng) -> usize {
     let mut decision = 0;
     
     // go towards position_aim
@@ -604,10 +541,10 @@ fn ghost_to_position(position_aim: Vec2, current_position: Vec2, available_direc
         } else {
             // neither choice is any good, choose at random
             if available_directions.len() > 1 {
-                decision = thread_rng().gen_range(0..available_directions.len());
+                decision = rng.gen_index(available_directions.len());
             }
         }
     }
 
-    return decision;
+    decision
 }
\ No newline at end of file
@@ -0,0 +1,175 @@
+use bevy::prelude::*;
+
+use crate::fixedtimestep::{Interpolated, SimulationSet};
+use crate::gamelogic::{GameLogic, GameSimulation, Horizontal, OnGameplayScreen, Vertical};
+use crate::ghost::GhostActionsStatus;
+use crate::{AnimationIndicies, AnimationTimer};
+
+/*
+ * Ghost sprites
+ *
+ * Each ghost is drawn as a body and a pair of eyes on top, both just follow the ghost with the same index in the
+ * simulation around - the body colour and the way the eyes look come from what the ghost is doing.
+ */
+
+pub struct GhostSpritesPlugin;
+
+impl Plugin for GhostSpritesPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, update_ghost_sprites.in_set(SimulationSet::UpdateSprites).run_if(resource_exists::<GameSimulation>));
+    }
+}
+
+#[derive(Component)]
+pub struct GhostBody {
+    pub index: usize,
+    pub base_colour: Color,
+}
+
+#[derive(Component)]
+pub struct GhostEyes {
+    pub index: usize,
+}
+
+const WEAKENED_COLOUR: Color = Color::srgb(0.082, 0.141, 0.380); // a dark navy colour
+const FLASHING_COLOUR: Color = Color::WHITE;
+
+fn ghost_colour(name: &str) -> Color {
+    match name {
+        "Blinky" => Color::srgb (1.0, 0.0, 0.0), // red
+        "Inky" => Color::srgb (0.0, 1.0, 1.0), // cyan
+        "Pinky" => Color::srgb (1.0, 0.0, 1.0), // pink
+        "Clyde" => Color::srgb (1.0, 1.0, 0.0), // yellow
+        _ => Color::WHITE,
+    }
+}
+
+pub fn spawn_ghosts(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    mut texture_atlases: ResMut<Assets<TextureAtlasLayout>>,
+    game_logic: Query<&GameLogic>,
+    simulation: Res<GameSimulation>,
+) {
+    let game_logic = game_logic.single();
+
+    for (index, ghost) in simulation.ghosts.iter().enumerate() {
+
+        let screen_pos = game_logic.get_screen_coords(ghost.position.x, ghost.position.y);
+        // each ghost gets its own layer so they overlap neatly
+        let transform = Transform::from_xyz(screen_pos.x, screen_pos.y, 0.01101 + (index as f32 * 0.00001))
+            .with_scale(Vec3::splat(game_logic.tile_scale()));
+
+        let mut eyes_transform = transform;
+        eyes_transform.translation.z += 0.00001;
+
+        let ghost_size = UVec2::new(22, 24);
+        let ghost_anim_indicies = AnimationIndicies {first: 0, last: 4};
+
+        let eyes_indicies = AnimationIndicies {first: 0, last: 4};
+
+        let base_colour = ghost_colour(&ghost.name);
+
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: base_colour,
+                    ..default()
+                },
+                transform,
+                texture: asset_server.load("GhostBody_SpriteSheet.png"),
+                ..default()
+            },
+            TextureAtlas {
+                layout: texture_atlases.add(TextureAtlasLayout::from_grid(
+                ghost_size,
+                1,
+                5,
+                None, None)),
+                index: ghost_anim_indicies.first,
+            },
+            ghost_anim_indicies,
+            AnimationTimer(Timer::from_seconds(0.1, TimerMode::Repeating)),
+            GhostBody { index, base_colour },
+            Interpolated::new(screen_pos),
+            OnGameplayScreen,
+        ));
+
+        commands.spawn((
+            SpriteBundle {
+                transform: eyes_transform,
+                texture: asset_server.load("GhostEyes_SpriteSheet.png"),
+                ..default()
+            },
+            TextureAtlas {
+                layout: texture_atlases.add(TextureAtlasLayout::from_grid(
+                    ghost_size,
+                    1,
+                    5,
+                    None, None)),
+                index: eyes_indicies.first,
+            },
+            eyes_indicies,
+            GhostEyes { index },
+            Interpolated::new(screen_pos),
+            OnGameplayScreen,
+        ));
+    }
+}
+
+/*
+ * Move the ghost sprites to where the ghosts are in the simulation
+ */
+#[allow(clippy::type_complexity)]
+fn update_ghost_sprites(
+    simulation: Res<GameSimulation>,
+    game_logic: Query<&GameLogic>,
+    mut ghost_bodys: Query<(&GhostBody, &mut Transform, &mut Sprite), Without<GhostEyes>>,
+    mut ghost_eyes: Query<(&GhostEyes, &mut Transform, &AnimationIndicies, &mut TextureAtlas), Without<GhostBody>>,
+) {
+    let Ok(game_logic) = game_logic.get_single() else {
+        return;
+    };
+
+    for (ghost_body, mut transform, mut sprite) in &mut ghost_bodys {
+        let Some(ghost) = simulation.ghosts.get(ghost_body.index) else {
+            continue;
+        };
+
+        let screen_pos = game_logic.get_screen_coords(ghost.position.x, ghost.position.y);
+        transform.translation.x = screen_pos.x;
+        transform.translation.y = screen_pos.y;
+
+        sprite.color = match ghost.actions_status {
+            GhostActionsStatus::Weakened if simulation.ghost_flashing(ghost) => FLASHING_COLOUR,
+            GhostActionsStatus::Weakened => WEAKENED_COLOUR,
+            // the body has been eaten, only the eyes go back to the pen
+            GhostActionsStatus::RunningToPen | GhostActionsStatus::GoingIntoPen => Color::srgba(0.0, 0.0, 0.0, 0.0),
+            _ => ghost_body.base_colour,
+        };
+    }
+
+    for (ghost_eyes, mut transform, indices, mut atlas) in &mut ghost_eyes {
+        let Some(ghost) = simulation.ghosts.get(ghost_eyes.index) else {
+            continue;
+        };
+
+        let screen_pos = game_logic.get_screen_coords(ghost.position.x, ghost.position.y);
+        transform.translation.x = screen_pos.x;
+        transform.translation.y = screen_pos.y;
+
+        // update eyes direction
+        let sprite_index =
+
+            if ghost.direction_of_travel.horizontal ==      Horizontal::Right { 0 } // right
+            else if ghost.direction_of_travel.horizontal == Horizontal::Left  { 1 } // left
+            else if ghost.direction_of_travel.vertical ==   Vertical::Up      { 2 } // up
+            else if ghost.direction_of_travel.vertical ==   Vertical::Down    { 3 } // down
+            else { 4 }; // no direction
+
+        // if sprite index is valid, update the sprite index
+        if sprite_index >= indices.first && sprite_index <= indices.last {
+            atlas.index = sprite_index;
+        }
+    }
+}
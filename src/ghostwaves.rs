@@ -0,0 +1,80 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+/*
+ * Scatter / chase waves
+ *
+ * Out of the pen the ghosts alternate between scattering to their own corners of the maze and chasing the player.
+ * The length of each wave depends on the level, the last chase wave goes on forever.
+ */
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GhostWaveMode {
+    Scatter,
+    Chase,
+}
+
+pub struct GhostWaves {
+    // each wave and how long it lasts in seconds (None lasts until the end of the level)
+    waves: Vec<(GhostWaveMode, Option<f32>)>,
+    current_wave: usize,
+    timer: Option<Timer>,
+}
+
+impl GhostWaves {
+    /*
+     * The arcade wave timings - the scatter waves get shorter and the chase waves longer as the levels go up
+     */
+    pub fn for_level(level: u32) -> Self {
+        use GhostWaveMode::{Chase, Scatter};
+
+        let waves = match level {
+            0..=1 => vec![(Scatter, Some(7.0)), (Chase, Some(20.0)), (Scatter, Some(7.0)), (Chase, Some(20.0)),
+                          (Scatter, Some(5.0)), (Chase, Some(20.0)), (Scatter, Some(5.0)), (Chase, None)],
+            2..=4 => vec![(Scatter, Some(7.0)), (Chase, Some(20.0)), (Scatter, Some(7.0)), (Chase, Some(20.0)),
+                          (Scatter, Some(5.0)), (Chase, Some(1033.0)), (Scatter, Some(1.0 / 60.0)), (Chase, None)],
+            _ => vec![(Scatter, Some(5.0)), (Chase, Some(20.0)), (Scatter, Some(5.0)), (Chase, Some(20.0)),
+                      (Scatter, Some(5.0)), (Chase, Some(1037.0)), (Scatter, Some(1.0 / 60.0)), (Chase, None)],
+        };
+
+        let mut ghost_waves = GhostWaves { waves, current_wave: 0, timer: None };
+        ghost_waves.start_wave(0);
+        ghost_waves
+    }
+
+    fn start_wave(&mut self, wave: usize) {
+        self.current_wave = wave;
+        self.timer = self.waves[wave].1.map(|seconds| Timer::from_seconds(seconds, TimerMode::Once));
+    }
+
+    pub fn mode(&self) -> GhostWaveMode {
+        self.waves[self.current_wave].0
+    }
+
+    /*
+     * Move the waves on by the given amount of time
+     * Returns true if the mode has changed
+     */
+    pub fn tick(&mut self, delta: Duration) -> bool {
+        let Some(timer) = &mut self.timer else {
+            // the final wave never ends
+            return false;
+        };
+
+        timer.tick(delta);
+        if timer.finished() && self.current_wave + 1 < self.waves.len() {
+            let previous_mode = self.mode();
+            self.start_wave(self.current_wave + 1);
+            return self.mode() != previous_mode;
+        }
+
+        false
+    }
+}
+
+impl Default for GhostWaves {
+    fn default() -> Self {
+        GhostWaves::for_level(1)
+    }
+}
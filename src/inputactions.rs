@@ -0,0 +1,383 @@
+use bevy::{ecs::system::SystemParam, input::InputSystem, prelude::*};
+
+/*
+ * Input actions
+ *
+ * Nothing reads the keyboard or gamepad directly, everything asks whether one of these actions is held down or has
+ * just been pressed. Each action is bound to some keyboard keys and gamepad buttons, which can be changed from the
+ * options screen and are saved in the settings file (see settings.rs). The defaults are:
+ *  up / down / left / right    arrow keys, WASD or the d-pad
+ *  start                       Space or the start button
+ *  pause                       Escape, P or the start button
+ *  confirm                     Enter, Space or the A (south) button
+ *  back                        Escape, Backspace or the B (east) button
+ *  player two directions       IJKL or the d-pad
+ *
+ * The left stick of any gamepad always works for up, down, left and right as well. When two players are playing
+ * at once player one uses the first gamepad and player two the second, along with their own keys.
+ */
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum InputAction {
+    Up,
+    Down,
+    Left,
+    Right,
+    Start,
+    Pause,
+    Confirm,
+    Back,
+    PlayerTwoUp,
+    PlayerTwoDown,
+    PlayerTwoLeft,
+    PlayerTwoRight,
+}
+
+impl InputAction {
+    pub const ALL: [InputAction; 12] = [
+        InputAction::Up,
+        InputAction::Down,
+        InputAction::Left,
+        InputAction::Right,
+        InputAction::Start,
+        InputAction::Pause,
+        InputAction::Confirm,
+        InputAction::Back,
+        InputAction::PlayerTwoUp,
+        InputAction::PlayerTwoDown,
+        InputAction::PlayerTwoLeft,
+        InputAction::PlayerTwoRight,
+    ];
+
+    // up, down, left and right for each player
+    pub const DIRECTIONS: [[InputAction; 4]; 2] = [
+        [InputAction::Up, InputAction::Down, InputAction::Left, InputAction::Right],
+        [InputAction::PlayerTwoUp, InputAction::PlayerTwoDown, InputAction::PlayerTwoLeft, InputAction::PlayerTwoRight],
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            InputAction::Up => "up",
+            InputAction::Down => "down",
+            InputAction::Left => "left",
+            InputAction::Right => "right",
+            InputAction::Start => "start",
+            InputAction::Pause => "pause",
+            InputAction::Confirm => "confirm",
+            InputAction::Back => "back",
+            InputAction::PlayerTwoUp => "p2_up",
+            InputAction::PlayerTwoDown => "p2_down",
+            InputAction::PlayerTwoLeft => "p2_left",
+            InputAction::PlayerTwoRight => "p2_right",
+        }
+    }
+
+    fn index(&self) -> usize {
+        InputAction::ALL.iter().position(|action| action == self).unwrap_or(0)
+    }
+
+    fn default_keys(&self) -> Vec<KeyCode> {
+        match self {
+            InputAction::Up => vec![KeyCode::ArrowUp, KeyCode::KeyW],
+            InputAction::Down => vec![KeyCode::ArrowDown, KeyCode::KeyS],
+            InputAction::Left => vec![KeyCode::ArrowLeft, KeyCode::KeyA],
+            InputAction::Right => vec![KeyCode::ArrowRight, KeyCode::KeyD],
+            InputAction::Start => vec![KeyCode::Space],
+            InputAction::Pause => vec![KeyCode::Escape, KeyCode::KeyP],
+            InputAction::Confirm => vec![KeyCode::Enter, KeyCode::Space],
+            InputAction::Back => vec![KeyCode::Escape, KeyCode::Backspace],
+            InputAction::PlayerTwoUp => vec![KeyCode::KeyI],
+            InputAction::PlayerTwoDown => vec![KeyCode::KeyK],
+            InputAction::PlayerTwoLeft => vec![KeyCode::KeyJ],
+            InputAction::PlayerTwoRight => vec![KeyCode::KeyL],
+        }
+    }
+
+    fn default_buttons(&self) -> Vec<GamepadButtonType> {
+        match self {
+            InputAction::Up => vec![GamepadButtonType::DPadUp],
+            InputAction::Down => vec![GamepadButtonType::DPadDown],
+            InputAction::Left => vec![GamepadButtonType::DPadLeft],
+            InputAction::Right => vec![GamepadButtonType::DPadRight],
+            InputAction::Start => vec![GamepadButtonType::Start],
+            InputAction::Pause => vec![GamepadButtonType::Start],
+            InputAction::Confirm => vec![GamepadButtonType::South],
+            InputAction::Back => vec![GamepadButtonType::East],
+            InputAction::PlayerTwoUp => vec![GamepadButtonType::DPadUp],
+            InputAction::PlayerTwoDown => vec![GamepadButtonType::DPadDown],
+            InputAction::PlayerTwoLeft => vec![GamepadButtonType::DPadLeft],
+            InputAction::PlayerTwoRight => vec![GamepadButtonType::DPadRight],
+        }
+    }
+}
+
+// the keys and buttons that can be bound to actions, the names used in the settings file are the debug names of these
+const BINDABLE_KEYS: [KeyCode; 66] = [
+    KeyCode::KeyA, KeyCode::KeyB, KeyCode::KeyC, KeyCode::KeyD, KeyCode::KeyE, KeyCode::KeyF, KeyCode::KeyG,
+    KeyCode::KeyH, KeyCode::KeyI, KeyCode::KeyJ, KeyCode::KeyK, KeyCode::KeyL, KeyCode::KeyM, KeyCode::KeyN,
+    KeyCode::KeyO, KeyCode::KeyP, KeyCode::KeyQ, KeyCode::KeyR, KeyCode::KeyS, KeyCode::KeyT, KeyCode::KeyU,
+    KeyCode::KeyV, KeyCode::KeyW, KeyCode::KeyX, KeyCode::KeyY, KeyCode::KeyZ,
+    KeyCode::Digit0, KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3, KeyCode::Digit4,
+    KeyCode::Digit5, KeyCode::Digit6, KeyCode::Digit7, KeyCode::Digit8, KeyCode::Digit9,
+    KeyCode::Numpad0, KeyCode::Numpad1, KeyCode::Numpad2, KeyCode::Numpad3, KeyCode::Numpad4,
+    KeyCode::Numpad5, KeyCode::Numpad6, KeyCode::Numpad7, KeyCode::Numpad8, KeyCode::Numpad9,
+    KeyCode::ArrowUp, KeyCode::ArrowDown, KeyCode::ArrowLeft, KeyCode::ArrowRight,
+    KeyCode::Space, KeyCode::Enter, KeyCode::NumpadEnter, KeyCode::Escape, KeyCode::Backspace, KeyCode::Tab,
+    KeyCode::ShiftLeft, KeyCode::ShiftRight, KeyCode::ControlLeft, KeyCode::ControlRight, KeyCode::AltLeft, KeyCode::AltRight,
+    KeyCode::Comma, KeyCode::Period, KeyCode::Slash, KeyCode::Semicolon,
+];
+
+const BINDABLE_BUTTONS: [GamepadButtonType; 19] = [
+    GamepadButtonType::South, GamepadButtonType::East, GamepadButtonType::North, GamepadButtonType::West,
+    GamepadButtonType::C, GamepadButtonType::Z,
+    GamepadButtonType::LeftTrigger, GamepadButtonType::LeftTrigger2, GamepadButtonType::RightTrigger, GamepadButtonType::RightTrigger2,
+    GamepadButtonType::Select, GamepadButtonType::Start, GamepadButtonType::Mode,
+    GamepadButtonType::LeftThumb, GamepadButtonType::RightThumb,
+    GamepadButtonType::DPadUp, GamepadButtonType::DPadDown, GamepadButtonType::DPadLeft, GamepadButtonType::DPadRight,
+];
+
+// how far the stick has to be pushed to count as a direction being held down
+const STICK_THRESHOLD: f32 = 0.5;
+
+pub fn key_name(key: KeyCode) -> String {
+    format!("{:?}", key)
+}
+
+pub fn button_name(button: GamepadButtonType) -> String {
+    format!("{:?}", button)
+}
+
+fn key_from_name(name: &str) -> Option<KeyCode> {
+    BINDABLE_KEYS.into_iter().find(|key| key_name(*key) == name)
+}
+
+fn button_from_name(name: &str) -> Option<GamepadButtonType> {
+    BINDABLE_BUTTONS.into_iter().find(|button| button_name(*button) == name)
+}
+
+/*
+ * A single key or gamepad button to bind to an action
+ */
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Binding {
+    Key(KeyCode),
+    Button(GamepadButtonType),
+}
+
+/*
+ * The keys and buttons for every action
+ */
+#[derive(Resource, Clone, PartialEq, Debug)]
+pub struct InputBindings {
+    keys: Vec<Vec<KeyCode>>,
+    buttons: Vec<Vec<GamepadButtonType>>,
+}
+
+impl Default for InputBindings {
+    fn default() -> Self {
+        InputBindings {
+            keys: InputAction::ALL.iter().map(|action| action.default_keys()).collect(),
+            buttons: InputAction::ALL.iter().map(|action| action.default_buttons()).collect(),
+        }
+    }
+}
+
+impl InputBindings {
+    pub fn keys(&self, action: InputAction) -> &[KeyCode] {
+        &self.keys[action.index()]
+    }
+
+    pub fn buttons(&self, action: InputAction) -> &[GamepadButtonType] {
+        &self.buttons[action.index()]
+    }
+
+    /*
+     * Bind a key or button to the action in place of the keys or buttons it had before
+     */
+    pub fn rebind(&mut self, action: InputAction, binding: Binding) {
+        match binding {
+            Binding::Key(key) => self.keys[action.index()] = vec![key],
+            Binding::Button(button) => self.buttons[action.index()] = vec![button],
+        }
+    }
+
+    /*
+     * The settings file lines for the bindings, "keys_up: ArrowUp KeyW" and "buttons_up: DPadUp"
+     */
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+
+        for action in InputAction::ALL {
+            let keys: Vec<String> = self.keys(action).iter().map(|key| key_name(*key)).collect();
+            let buttons: Vec<String> = self.buttons(action).iter().map(|button| button_name(*button)).collect();
+            text.push_str(&format!("keys_{}: {}\n", action.name(), keys.join(" ")));
+            text.push_str(&format!("buttons_{}: {}\n", action.name(), buttons.join(" ")));
+        }
+
+        text
+    }
+
+    /*
+     * Read a binding line from the settings file, None if the key isn't a binding and Some(false) if the value
+     * isn't valid
+     */
+    pub fn parse_setting(&mut self, key: &str, value: &str) -> Option<bool> {
+        if let Some(action) = key.strip_prefix("keys_").and_then(action_from_name) {
+            let keys: Option<Vec<KeyCode>> = value.split_whitespace().map(key_from_name).collect();
+            return Some(match keys {
+                Some(keys) => { self.keys[action.index()] = keys; true },
+                None => false,
+            });
+        }
+        if let Some(action) = key.strip_prefix("buttons_").and_then(action_from_name) {
+            let buttons: Option<Vec<GamepadButtonType>> = value.split_whitespace().map(button_from_name).collect();
+            return Some(match buttons {
+                Some(buttons) => { self.buttons[action.index()] = buttons; true },
+                None => false,
+            });
+        }
+
+        None
+    }
+}
+
+fn action_from_name(name: &str) -> Option<InputAction> {
+    InputAction::ALL.into_iter().find(|action| action.name() == name)
+}
+
+/*
+ * Which directions the left stick of any gamepad is pushed in, this frame and last frame, and of each gamepad
+ * on its own this frame
+ */
+#[derive(Resource, Default)]
+struct StickDirections {
+    held: [bool; 4],
+    previous: [bool; 4],
+    held_by_gamepad: Vec<(Gamepad, [bool; 4])>,
+}
+
+impl StickDirections {
+    fn direction_index(action: InputAction) -> Option<usize> {
+        match action {
+            InputAction::Up | InputAction::PlayerTwoUp => Some(0),
+            InputAction::Down | InputAction::PlayerTwoDown => Some(1),
+            InputAction::Left | InputAction::PlayerTwoLeft => Some(2),
+            InputAction::Right | InputAction::PlayerTwoRight => Some(3),
+            _ => None,
+        }
+    }
+
+    fn held(&self, action: InputAction) -> bool {
+        StickDirections::direction_index(action).is_some_and(|index| self.held[index])
+    }
+
+    fn held_by_gamepad(&self, action: InputAction, gamepad: Gamepad) -> bool {
+        let Some(index) = StickDirections::direction_index(action) else {
+            return false;
+        };
+        self.held_by_gamepad.iter().any(|(stick_gamepad, held)| *stick_gamepad == gamepad && held[index])
+    }
+
+    fn just_pressed(&self, action: InputAction) -> bool {
+        StickDirections::direction_index(action).is_some_and(|index| self.held[index] && !self.previous[index])
+    }
+}
+
+pub struct InputActionsPlugin;
+
+impl Plugin for InputActionsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<StickDirections>()
+            .add_systems(PreUpdate, update_stick_directions.after(InputSystem));
+    }
+}
+
+fn update_stick_directions(
+    gamepads: Res<Gamepads>,
+    axes: Res<Axis<GamepadAxis>>,
+    mut stick: ResMut<StickDirections>,
+) {
+    stick.previous = stick.held;
+    stick.held = [false; 4];
+    stick.held_by_gamepad.clear();
+
+    for gamepad in gamepads.iter() {
+        let x = axes.get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickX)).unwrap_or(0.0);
+        let y = axes.get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickY)).unwrap_or(0.0);
+
+        // up, down, left, right
+        let pushed = [y > STICK_THRESHOLD, y < -STICK_THRESHOLD, x < -STICK_THRESHOLD, x > STICK_THRESHOLD];
+        for (held, pushed) in stick.held.iter_mut().zip(pushed) {
+            *held |= pushed;
+        }
+        stick.held_by_gamepad.push((gamepad, pushed));
+    }
+}
+
+#[derive(SystemParam)]
+pub struct ActionInput<'w> {
+    bindings: Res<'w, InputBindings>,
+    keys: Res<'w, ButtonInput<KeyCode>>,
+    gamepads: Res<'w, Gamepads>,
+    gamepad_buttons: Res<'w, ButtonInput<GamepadButton>>,
+    stick: Res<'w, StickDirections>,
+}
+
+impl ActionInput<'_> {
+    pub fn bindings(&self) -> &InputBindings {
+        &self.bindings
+    }
+
+    pub fn pressed(&self, action: InputAction) -> bool {
+        let bindings = &self.bindings;
+
+        self.keys.any_pressed(bindings.keys(action).iter().copied()) ||
+            self.stick.held(action) ||
+            self.gamepads.iter().any(|gamepad| {
+                bindings.buttons(action).iter().any(|button_type| self.gamepad_buttons.pressed(GamepadButton::new(gamepad, *button_type)))
+            })
+    }
+
+    pub fn just_pressed(&self, action: InputAction) -> bool {
+        let bindings = &self.bindings;
+
+        self.keys.any_just_pressed(bindings.keys(action).iter().copied()) ||
+            self.stick.just_pressed(action) ||
+            self.gamepads.iter().any(|gamepad| {
+                bindings.buttons(action).iter().any(|button_type| self.gamepad_buttons.just_pressed(GamepadButton::new(gamepad, *button_type)))
+            })
+    }
+
+    /*
+     * Whether the action is held down by the given player (starting from 0) - on the keyboard, or on the gamepad that
+     * is theirs when more than one player is playing at once
+     */
+    pub fn pressed_by_player(&self, action: InputAction, player: usize) -> bool {
+        let bindings = &self.bindings;
+
+        // the gamepads are handed out to the players in the order they were connected
+        let mut gamepads: Vec<Gamepad> = self.gamepads.iter().collect();
+        gamepads.sort_by_key(|gamepad| gamepad.id);
+
+        self.keys.any_pressed(bindings.keys(action).iter().copied()) ||
+            gamepads.get(player).is_some_and(|gamepad| {
+                self.stick.held_by_gamepad(action, *gamepad) ||
+                    bindings.buttons(action).iter().any(|button_type| self.gamepad_buttons.pressed(GamepadButton::new(*gamepad, *button_type)))
+            })
+    }
+
+    /*
+     * Any key or button that has just been pressed and can be bound to an action, for changing the bindings
+     */
+    pub fn just_pressed_binding(&self) -> Option<Binding> {
+        if let Some(key) = BINDABLE_KEYS.into_iter().find(|key| self.keys.just_pressed(*key)) {
+            return Some(Binding::Key(key));
+        }
+
+        self.gamepads.iter().find_map(|gamepad| {
+            BINDABLE_BUTTONS.into_iter()
+                .find(|button_type| self.gamepad_buttons.just_pressed(GamepadButton::new(gamepad, *button_type)))
+                .map(Binding::Button)
+        })
+    }
+}
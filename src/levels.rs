@@ -0,0 +1,108 @@
+use crate::fruit::Fruit;
+
+/*
+ * Difficulty progression
+ *
+ * Everything that changes from one level to the next comes from this table, the last row is used for every level
+ * past the end of it. Speeds are in tiles per second and times in seconds.
+ */
+
+#[derive(Clone, Copy, Debug)]
+pub struct LevelSettings {
+    pub player_speed: f32,
+    pub ghost_speed: f32,
+    // how long the ghosts stay weakened after a ghost weakness token is eaten
+    pub frightened_time: f32,
+    // the weakened ghosts flash this many times over the last few seconds to warn that they are about to recover
+    pub frightened_flash_time: f32,
+    pub frightened_flashes: u32,
+    // how long each ghost (Blinky, Inky, Pinky, Clyde) waits in the pen before coming out
+    pub pen_release: [f32; 4],
+    pub fruit: Fruit,
+}
+
+const fn level(
+    player_speed: f32,
+    ghost_speed: f32,
+    (frightened_time, frightened_flash_time, frightened_flashes): (f32, f32, u32),
+    pen_release: [f32; 4],
+    fruit: Fruit,
+) -> LevelSettings {
+    LevelSettings { player_speed, ghost_speed, frightened_time, frightened_flash_time, frightened_flashes, pen_release, fruit }
+}
+
+const LEVELS: [LevelSettings; 13] = [
+    // frightened is (time, time spent flashing at the end, number of flashes)
+    //     player  ghost   frightened       pen release             fruit
+    level(6.0,    4.0,    (8.0, 2.0, 5),   [1.0, 5.0, 9.0, 13.0],  Fruit::Cherry),        // 1
+    level(6.25,   4.5,    (7.0, 2.0, 5),   [1.0, 4.0, 7.0, 10.0],  Fruit::Strawberry),    // 2
+    level(6.25,   4.75,   (6.0, 2.0, 5),   [1.0, 3.0, 5.0, 7.0],   Fruit::Orange),        // 3
+    level(6.5,    4.75,   (5.0, 2.0, 5),   [1.0, 3.0, 5.0, 7.0],   Fruit::Orange),        // 4
+    level(6.5,    5.0,    (4.0, 2.0, 5),   [1.0, 2.0, 3.0, 4.0],   Fruit::Apple),         // 5
+    level(6.5,    5.0,    (4.0, 2.0, 5),   [1.0, 2.0, 3.0, 4.0],   Fruit::Apple),         // 6
+    level(6.75,   5.25,   (3.0, 1.5, 5),   [0.5, 1.5, 2.5, 3.5],   Fruit::Melon),         // 7
+    level(6.75,   5.25,   (3.0, 1.5, 5),   [0.5, 1.5, 2.5, 3.5],   Fruit::Melon),         // 8
+    level(6.75,   5.5,    (2.0, 1.2, 3),   [0.5, 1.0, 1.5, 2.0],   Fruit::Galaxian),      // 9
+    level(7.0,    5.5,    (2.0, 1.2, 3),   [0.5, 1.0, 1.5, 2.0],   Fruit::Galaxian),      // 10
+    level(7.0,    5.75,   (1.5, 1.2, 3),   [0.5, 1.0, 1.5, 2.0],   Fruit::Bell),          // 11
+    level(7.0,    5.75,   (1.5, 1.2, 3),   [0.5, 1.0, 1.5, 2.0],   Fruit::Bell),          // 12
+    level(7.0,    6.0,    (1.0, 1.0, 3),   [0.5, 1.0, 1.5, 2.0],   Fruit::Key),           // 13 onwards
+];
+
+impl LevelSettings {
+    /*
+     * The settings for the given level, starting from 1
+     */
+    pub fn for_level(level: u32) -> LevelSettings {
+        let index = (level.max(1) as usize - 1).min(LEVELS.len() - 1);
+        LEVELS[index]
+    }
+}
+
+/*
+ * How hard the game is on top of the level table - easier ghosts are slower, stay weakened for longer and take longer
+ * to come out of the pen
+ */
+#[derive(Clone, Copy, Default, Eq, PartialEq, Hash, Debug)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl Difficulty {
+    pub const ALL: [Difficulty; 3] = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Difficulty> {
+        Difficulty::ALL.into_iter().find(|difficulty| difficulty.name() == name)
+    }
+
+    /*
+     * The level settings changed to suit this difficulty
+     */
+    pub fn apply(&self, settings: LevelSettings) -> LevelSettings {
+        // (ghost speed, frightened time, pen release time) multipliers
+        let (ghost_speed, frightened_time, pen_release) = match self {
+            Difficulty::Easy => (0.9, 1.5, 1.5),
+            Difficulty::Normal => return settings,
+            Difficulty::Hard => (1.1, 0.6, 0.6),
+        };
+
+        LevelSettings {
+            ghost_speed: settings.ghost_speed * ghost_speed,
+            frightened_time: settings.frightened_time * frightened_time,
+            frightened_flash_time: settings.frightened_flash_time.min(settings.frightened_time * frightened_time),
+            pen_release: settings.pen_release.map(|time| time * pen_release),
+            ..settings
+        }
+    }
+}
@@ -0,0 +1,448 @@
+use bevy::prelude::*;
+
+use crate::gamestates::{despawn_screen, GameMode, GameState};
+use crate::maze::maze_loaded;
+use crate::inputactions::{button_name, key_name, ActionInput, InputAction, InputBindings};
+use crate::scoreboard::load_leaderboard;
+use crate::settings::{co_op_scores_name, on_off, Settings};
+
+/*
+ * Main menu
+ *
+ * The first thing shown when the game starts and where it goes back to after a game. Up and down pick an item,
+ * select chooses it and left and right change the game mode. The best few scores from the leaderboard are shown
+ * underneath, the high scores page shows all of them. The options page changes the settings, which are saved when
+ * going back from it, and has a controls page for changing the keys and buttons for each input action.
+ */
+
+// how many of the leaderboard entries are shown on the main page
+const TOP_SCORES_SHOWN: usize = 5;
+
+const TITLE_COLOUR: Color = Color::srgb(1.0, 1.0, 0.0);
+const SELECTED_COLOUR: Color = Color::srgb(1.0, 1.0, 0.0);
+const HINT_COLOUR: Color = Color::srgb(0.6, 0.6, 0.6);
+
+// how long to wait for a key or button when changing a binding before giving up
+const REBIND_TIME: f32 = 5.0;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum MainMenuItem {
+    StartGame,
+    GameMode,
+    HighScores,
+    Options,
+    Quit,
+}
+
+const MAIN_MENU_ITEMS: [MainMenuItem; 5] = [
+    MainMenuItem::StartGame,
+    MainMenuItem::GameMode,
+    MainMenuItem::HighScores,
+    MainMenuItem::Options,
+    MainMenuItem::Quit,
+];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum OptionsItem {
+    WindowSize,
+    Lives,
+    Difficulty,
+    InputBuffer,
+    Cornering,
+    CoOpScores,
+    Controls,
+    Back,
+}
+
+const OPTIONS_ITEMS: [OptionsItem; 8] = [
+    OptionsItem::WindowSize,
+    OptionsItem::Lives,
+    OptionsItem::Difficulty,
+    OptionsItem::InputBuffer,
+    OptionsItem::Cornering,
+    OptionsItem::CoOpScores,
+    OptionsItem::Controls,
+    OptionsItem::Back,
+];
+
+// the controls page has a row for each input action followed by these
+const CONTROLS_RESET_ROW: usize = InputAction::ALL.len();
+const CONTROLS_BACK_ROW: usize = InputAction::ALL.len() + 1;
+const CONTROLS_ROWS: usize = InputAction::ALL.len() + 2;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum MenuPage {
+    Main,
+    HighScores,
+    Options,
+    Controls,
+}
+
+#[derive(Resource)]
+struct MainMenu {
+    page: MenuPage,
+    selection: usize,
+    options_selection: usize,
+    controls_selection: usize,
+    // waiting for a key or button to bind to this action
+    rebinding: Option<InputAction>,
+}
+
+impl Default for MainMenu {
+    fn default() -> Self {
+        MainMenu { page: MenuPage::Main, selection: 0, options_selection: 0, controls_selection: 0, rebinding: None }
+    }
+}
+
+// the saved leaderboard, read each time the menu is shown
+#[derive(Resource, Default)]
+struct MenuLeaderboard(Vec<(String, i32)>);
+
+#[derive(Component)]
+struct OnMainMenuScreen;
+
+pub struct MainMenuPlugin;
+
+impl Plugin for MainMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameMode>()
+            .init_resource::<MenuLeaderboard>()
+            .init_resource::<MainMenu>()
+            .add_systems(OnEnter(GameState::MainMenu), main_menu_setup)
+            // starting a game needs the maze to have loaded
+            .add_systems(Update, main_menu_input.run_if(in_state(GameState::MainMenu).and_then(maze_loaded)))
+            .add_systems(Update, draw_main_menu.run_if(in_state(GameState::MainMenu)))
+            .add_systems(OnExit(GameState::MainMenu), despawn_screen::<OnMainMenuScreen>);
+    }
+}
+
+fn main_menu_setup(
+    mut commands: Commands,
+    mut menu: ResMut<MainMenu>,
+    mut leaderboard: ResMut<MenuLeaderboard>,
+) {
+    *menu = MainMenu::default();
+    leaderboard.0 = load_leaderboard();
+
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                width: Val::Percent(100.0),
+                height: Val::Percent(100.0),
+                flex_direction: FlexDirection::Column,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                row_gap: Val::Px(6.0),
+                padding: UiRect::all(Val::Px(10.0)),
+                ..default()
+            },
+            background_color: Color::BLACK.into(),
+            ..default()
+        },
+        OnMainMenuScreen,
+    ));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn main_menu_input(
+    mut commands: Commands,
+    input: ActionInput,
+    mut menu: ResMut<MainMenu>,
+    mut game_mode: ResMut<GameMode>,
+    mut settings: ResMut<Settings>,
+    mut game_state: ResMut<NextState<GameState>>,
+    mut app_exit: EventWriter<AppExit>,
+    time: Res<Time>,
+    mut rebind_timer: Local<Timer>,
+) {
+    match menu.page {
+        MenuPage::Main => {},
+        MenuPage::HighScores => {
+            if input.just_pressed(InputAction::Back) || input.just_pressed(InputAction::Confirm) {
+                menu.page = MenuPage::Main;
+            }
+            return;
+        },
+        MenuPage::Options => {
+            if options_input(&input, &mut menu, &mut settings) {
+                settings.save(input.bindings());
+                menu.page = MenuPage::Main;
+            }
+            return;
+        },
+        MenuPage::Controls => {
+            if let Some(action) = menu.rebinding {
+                rebind_timer.tick(time.delta());
+
+                if let Some(binding) = input.just_pressed_binding() {
+                    // the bindings are being read by the input, so the new ones go in once this system has finished
+                    let mut bindings = input.bindings().clone();
+                    bindings.rebind(action, binding);
+                    commands.insert_resource(bindings);
+                    menu.rebinding = None;
+                } else if rebind_timer.finished() {
+                    menu.rebinding = None;
+                }
+                return;
+            }
+
+            if input.just_pressed(InputAction::Back) || (input.just_pressed(InputAction::Confirm) && menu.controls_selection == CONTROLS_BACK_ROW) {
+                menu.page = MenuPage::Options;
+                return;
+            }
+            if input.just_pressed(InputAction::Up) {
+                menu.controls_selection = (menu.controls_selection + CONTROLS_ROWS - 1) % CONTROLS_ROWS;
+            }
+            if input.just_pressed(InputAction::Down) {
+                menu.controls_selection = (menu.controls_selection + 1) % CONTROLS_ROWS;
+            }
+            if input.just_pressed(InputAction::Confirm) {
+                if menu.controls_selection == CONTROLS_RESET_ROW {
+                    commands.insert_resource(InputBindings::default());
+                } else {
+                    menu.rebinding = Some(InputAction::ALL[menu.controls_selection]);
+                    *rebind_timer = Timer::from_seconds(REBIND_TIME, TimerMode::Once);
+                }
+            }
+            return;
+        },
+    }
+
+    if input.just_pressed(InputAction::Up) {
+        menu.selection = (menu.selection + MAIN_MENU_ITEMS.len() - 1) % MAIN_MENU_ITEMS.len();
+    }
+    if input.just_pressed(InputAction::Down) {
+        menu.selection = (menu.selection + 1) % MAIN_MENU_ITEMS.len();
+    }
+
+    let item = MAIN_MENU_ITEMS[menu.selection];
+
+    if item == MainMenuItem::GameMode {
+        if input.just_pressed(InputAction::Left) {
+            *game_mode = game_mode.cycle(false);
+        }
+        if input.just_pressed(InputAction::Right) {
+            *game_mode = game_mode.cycle(true);
+        }
+    }
+
+    if !input.just_pressed(InputAction::Confirm) {
+        return;
+    }
+
+    match item {
+        MainMenuItem::StartGame => game_state.set(GameState::LevelSetup),
+        MainMenuItem::GameMode => *game_mode = game_mode.cycle(true),
+        MainMenuItem::HighScores => menu.page = MenuPage::HighScores,
+        MainMenuItem::Options => {
+            menu.page = MenuPage::Options;
+            menu.options_selection = 0;
+        },
+        MainMenuItem::Quit => {
+            app_exit.send(AppExit::Success);
+        },
+    }
+}
+
+/*
+ * Move around the options and change them, true when going back to the main page
+ */
+fn options_input(input: &ActionInput, menu: &mut MainMenu, settings: &mut Settings) -> bool {
+    if input.just_pressed(InputAction::Back) {
+        return true;
+    }
+
+    if input.just_pressed(InputAction::Up) {
+        menu.options_selection = (menu.options_selection + OPTIONS_ITEMS.len() - 1) % OPTIONS_ITEMS.len();
+    }
+    if input.just_pressed(InputAction::Down) {
+        menu.options_selection = (menu.options_selection + 1) % OPTIONS_ITEMS.len();
+    }
+
+    let item = OPTIONS_ITEMS[menu.options_selection];
+    if item == OptionsItem::Back {
+        return input.just_pressed(InputAction::Confirm);
+    }
+    if item == OptionsItem::Controls {
+        if input.just_pressed(InputAction::Confirm) {
+            menu.page = MenuPage::Controls;
+            menu.controls_selection = 0;
+        }
+        return false;
+    }
+
+    // select moves on to the next value like right does
+    let forwards = if input.just_pressed(InputAction::Right) || input.just_pressed(InputAction::Confirm) {
+        true
+    } else if input.just_pressed(InputAction::Left) {
+        false
+    } else {
+        return false;
+    };
+
+    match item {
+        OptionsItem::WindowSize => settings.cycle_window_scale(forwards),
+        OptionsItem::Lives => settings.change_starting_lives(forwards),
+        OptionsItem::Difficulty => settings.cycle_difficulty(forwards),
+        OptionsItem::InputBuffer => settings.movement.input_buffer = !settings.movement.input_buffer,
+        OptionsItem::Cornering => settings.movement.cornering = !settings.movement.cornering,
+        OptionsItem::CoOpScores => settings.split_scores = !settings.split_scores,
+        OptionsItem::Controls | OptionsItem::Back => {},
+    }
+
+    false
+}
+
+fn menu_text(commands: &mut ChildBuilder, value: impl Into<String>, font_size: f32, color: Color) {
+    commands.spawn(
+        TextBundle {
+            text: Text::from_section(
+                value,
+                TextStyle {
+                    font_size,
+                    color,
+                    ..default()
+                }
+            ).with_justify(JustifyText::Center),
+            ..default()
+        }
+    );
+}
+
+/*
+ * The keys and buttons bound to an action, without the prefixes on the letter and number key names
+ */
+fn bindings_line(bindings: &InputBindings, action: InputAction) -> String {
+    let keys: Vec<String> = bindings.keys(action).iter()
+        .map(|key| key_name(*key).trim_start_matches("Key").trim_start_matches("Digit").to_string())
+        .collect();
+    let buttons: Vec<String> = bindings.buttons(action).iter().map(|button| button_name(*button)).collect();
+
+    format!("{} / {}", keys.join(" "), buttons.join(" "))
+}
+
+fn leaderboard_line(rank: usize, name: &str, score: i32) -> String {
+    format!("{:>2}. {:<3} {:>8}", rank, name, score)
+}
+
+/*
+ * Build the menu again whenever what is on it changes
+ */
+fn draw_main_menu(
+    mut commands: Commands,
+    menu: Res<MainMenu>,
+    game_mode: Res<GameMode>,
+    settings: Res<Settings>,
+    bindings: Res<InputBindings>,
+    leaderboard: Res<MenuLeaderboard>,
+    screen: Query<Entity, With<OnMainMenuScreen>>,
+) {
+    let Ok(screen) = screen.get_single() else {
+        return;
+    };
+    if !menu.is_changed() && !game_mode.is_changed() && !settings.is_changed() && !bindings.is_changed() && !leaderboard.is_changed() {
+        return;
+    }
+
+    commands.entity(screen).despawn_descendants().with_children(|commands| {
+        menu_text(commands, "PACMAN", 32.0, TITLE_COLOUR);
+
+        match menu.page {
+            MenuPage::Main => {
+                menu_text(commands, "", 10.0, Color::WHITE);
+
+                for (index, item) in MAIN_MENU_ITEMS.iter().enumerate() {
+                    let label = match item {
+                        MainMenuItem::StartGame => String::from("Start Game"),
+                        MainMenuItem::GameMode => format!("Game Mode: {}", game_mode.name()),
+                        MainMenuItem::HighScores => String::from("High Scores"),
+                        MainMenuItem::Options => String::from("Options"),
+                        MainMenuItem::Quit => String::from("Quit"),
+                    };
+
+                    if index == menu.selection {
+                        menu_text(commands, format!("> {} <", label), 20.0, SELECTED_COLOUR);
+                    } else {
+                        menu_text(commands, label, 20.0, Color::WHITE);
+                    }
+                }
+
+                menu_text(commands, "", 10.0, Color::WHITE);
+                menu_text(commands, "High Scores", 16.0, TITLE_COLOUR);
+                if leaderboard.0.is_empty() {
+                    menu_text(commands, "No scores yet", 16.0, Color::WHITE);
+                }
+                for (rank, (name, score)) in (1..).zip(leaderboard.0.iter().take(TOP_SCORES_SHOWN)) {
+                    menu_text(commands, leaderboard_line(rank, name, *score), 16.0, Color::WHITE);
+                }
+            },
+            MenuPage::HighScores => {
+                menu_text(commands, "High Scores", 20.0, Color::WHITE);
+                if leaderboard.0.is_empty() {
+                    menu_text(commands, "No scores yet", 16.0, Color::WHITE);
+                }
+                for (rank, (name, score)) in (1..).zip(leaderboard.0.iter()) {
+                    menu_text(commands, leaderboard_line(rank, name, *score), 16.0, Color::WHITE);
+                }
+                menu_text(commands, "", 10.0, Color::WHITE);
+                menu_text(commands, "> Back <", 20.0, SELECTED_COLOUR);
+            },
+            MenuPage::Options => {
+                menu_text(commands, "Options", 20.0, Color::WHITE);
+                menu_text(commands, "", 10.0, Color::WHITE);
+
+                for (index, item) in OPTIONS_ITEMS.iter().enumerate() {
+                    let label = match item {
+                        OptionsItem::WindowSize => format!("Window Size: {}x", settings.window_scale),
+                        OptionsItem::Lives => format!("Lives: {}", settings.starting_lives),
+                        OptionsItem::Difficulty => format!("Difficulty: {}", settings.difficulty.name()),
+                        OptionsItem::InputBuffer => format!("Input Buffer: {}", on_off(settings.movement.input_buffer)),
+                        OptionsItem::Cornering => format!("Cornering: {}", on_off(settings.movement.cornering)),
+                        OptionsItem::CoOpScores => format!("Co-op Scores: {}", co_op_scores_name(settings.split_scores)),
+                        OptionsItem::Controls => String::from("Controls"),
+                        OptionsItem::Back => String::from("Back"),
+                    };
+
+                    if index == menu.options_selection {
+                        menu_text(commands, format!("> {} <", label), 20.0, SELECTED_COLOUR);
+                    } else {
+                        menu_text(commands, label, 20.0, Color::WHITE);
+                    }
+                }
+            },
+            MenuPage::Controls => {
+                menu_text(commands, "Controls", 20.0, Color::WHITE);
+
+                for index in 0..CONTROLS_ROWS {
+                    let label = match index {
+                        CONTROLS_RESET_ROW => String::from("Reset to defaults"),
+                        CONTROLS_BACK_ROW => String::from("Back"),
+                        _ => {
+                            let action = InputAction::ALL[index];
+                            if menu.rebinding == Some(action) {
+                                format!("{}: ...", action.name())
+                            } else {
+                                format!("{}: {}", action.name(), bindings_line(&bindings, action))
+                            }
+                        },
+                    };
+
+                    if index == menu.controls_selection {
+                        menu_text(commands, format!("> {} <", label), 14.0, SELECTED_COLOUR);
+                    } else {
+                        menu_text(commands, label, 14.0, Color::WHITE);
+                    }
+                }
+            },
+        }
+
+        menu_text(commands, "", 10.0, Color::WHITE);
+        let hint = match menu.page {
+            MenuPage::Options => "Left / right to change, Escape / B to go back",
+            MenuPage::Controls if menu.rebinding.is_some() => "Press a key or button to use for it",
+            MenuPage::Controls => "Select an action to change its key or button",
+            _ => "Arrows / d-pad to move, Enter / A to select",
+        };
+        menu_text(commands, hint, 12.0, HINT_COLOUR);
+    });
+}
@@ -0,0 +1,485 @@
+use std::fmt;
+
+use bevy::{asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext}, prelude::*};
+
+use crate::gamelogic::{BlockCell, BlockReward, BlockType};
+
+/*
+ * Maze files are plain text: a header of "key: value" lines, a "---" separator and then the grid itself
+ *
+ *  ; lines starting with a semicolon in the header are comments
+ *  name: Classic
+ *  width: 26
+ *  height: 29
+ *  player_start: 12.5, 16
+ *  pen_exit: 12.5, 10
+ *  pen_centre: 12.5, 13
+ *  scatter_blinky: 25, 0
+ *  fruit: 12.5, 16
+ *  ---
+ *  ............##............
+ *
+ * Positions are gameboard coordinates (col, row) and can be between cells
+ * player_start, pen_exit and fruit must be on the path, pen_centre only has to be on the board as the inside of the pen
+ * is usually drawn as wall
+ * The scatter corners (scatter_blinky, scatter_pinky, scatter_inky, scatter_clyde) are optional and default to the
+ * corners of the maze, they can be off the board
+ * The bonus fruit appears at fruit (optional, defaults to player_start)
+ *
+ * Grid characters:
+ *  #       wall
+ *  =       ghost pen door (a wall that only the ghosts go through)
+ *  .       path with a point token
+ *  o       path with a ghost weakness token
+ *  _       path with nothing on it
+ *  0 - 9   warp tile, each digit must appear exactly twice and the two tiles warp to each other
+ */
+
+pub const DEFAULT_MAZE: &str = "levels/classic.maze";
+
+#[derive(Asset, TypePath)]
+pub struct Maze {
+    pub name: String,
+    pub width: usize,
+    pub height: usize,
+    // all of the cells row by row
+    pub game_blocks: Vec<BlockCell>,
+    pub player_start: Vec2,
+    pub pen_exit: Vec2,
+    pub pen_centre: Vec2,
+    pub scatter_corners: ScatterCorners,
+    pub fruit_position: Vec2,
+}
+
+/*
+ * The positions each of the ghosts heads for when they are scattering
+ */
+#[derive(Clone, Copy)]
+pub struct ScatterCorners {
+    pub blinky: Vec2,
+    pub pinky: Vec2,
+    pub inky: Vec2,
+    pub clyde: Vec2,
+}
+
+// warp tiles store their destination as u8 coordinates
+const MAX_MAZE_SIZE: usize = u8::MAX as usize + 1;
+
+#[derive(Resource)]
+pub struct MazeHandle(pub Handle<Maze>);
+
+/*
+ * The asset path of the maze to play
+ */
+#[derive(Resource)]
+pub struct MazePath(pub String);
+
+impl Default for MazePath {
+    fn default() -> Self {
+        MazePath(DEFAULT_MAZE.to_string())
+    }
+}
+
+#[derive(Debug)]
+pub struct MazeParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for MazeParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl std::error::Error for MazeParseError {}
+
+#[derive(Debug)]
+pub enum MazeLoaderError {
+    Io(std::io::Error),
+    Parse(MazeParseError),
+}
+
+impl fmt::Display for MazeLoaderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MazeLoaderError::Io(error) => write!(f, "could not read maze file: {}", error),
+            MazeLoaderError::Parse(error) => write!(f, "invalid maze file: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for MazeLoaderError {}
+
+impl From<std::io::Error> for MazeLoaderError {
+    fn from(error: std::io::Error) -> Self {
+        MazeLoaderError::Io(error)
+    }
+}
+
+#[derive(Default)]
+pub struct MazeLoader;
+
+impl AssetLoader for MazeLoader {
+    type Asset = Maze;
+    type Settings = ();
+    type Error = MazeLoaderError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Maze, MazeLoaderError> {
+        let mut source = String::new();
+        reader.read_to_string(&mut source).await?;
+
+        parse_maze(&source).map_err(MazeLoaderError::Parse)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["maze"]
+    }
+}
+
+pub struct MazePlugin;
+
+impl Plugin for MazePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Maze>()
+            .init_asset_loader::<MazeLoader>()
+            .init_resource::<MazePath>()
+            .add_systems(Startup, load_maze);
+    }
+}
+
+fn load_maze(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    maze_path: Res<MazePath>,
+) {
+    commands.insert_resource(MazeHandle(asset_server.load(maze_path.0.clone())));
+}
+
+/*
+ * Run condition - true once the current maze has finished loading
+ */
+pub fn maze_loaded(
+    maze_handle: Res<MazeHandle>,
+    mazes: Res<Assets<Maze>>,
+) -> bool {
+    mazes.contains(&maze_handle.0)
+}
+
+fn parse_error(line: usize, column: usize, message: impl Into<String>) -> MazeParseError {
+    MazeParseError { line, column, message: message.into() }
+}
+
+/*
+ * Parse the text of a maze file
+ * Line and column numbers in any error start from 1
+ */
+pub fn parse_maze(source: &str) -> Result<Maze, MazeParseError> {
+    let mut lines = source.lines().enumerate().map(|(index, line)| (index + 1, line));
+
+    let mut name = String::from("Unnamed");
+    let mut width: Option<usize> = None;
+    let mut height: Option<usize> = None;
+    let mut player_start: Option<Vec2> = None;
+    let mut pen_exit: Option<Vec2> = None;
+    let mut pen_centre: Option<Vec2> = None;
+    let mut scatter_blinky: Option<Vec2> = None;
+    let mut scatter_pinky: Option<Vec2> = None;
+    let mut scatter_inky: Option<Vec2> = None;
+    let mut scatter_clyde: Option<Vec2> = None;
+    let mut fruit_position: Option<Vec2> = None;
+    let mut last_header_line = 0;
+    // where each position was given in the header, to point at it if it turns out to be somewhere it can't be
+    let mut position_lines: Vec<(&str, Vec2, usize, usize)> = Vec::new();
+
+    // read the header up to the separator
+    loop {
+        let Some((line_number, line)) = lines.next() else {
+            return Err(parse_error(last_header_line + 1, 1, "missing '---' separator between the header and the grid"));
+        };
+        last_header_line = line_number;
+
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with(';') {
+            continue;
+        }
+        if trimmed == "---" {
+            break;
+        }
+
+        let Some((key, value)) = line.split_once(':') else {
+            return Err(parse_error(line_number, 1, "expected a 'key: value' header line"));
+        };
+        let value_column = key.len() + 2 + (value.len() - value.trim_start().len());
+        let value = value.trim();
+
+        match key.trim() {
+            "name" => name = value.to_string(),
+            "width" | "height" => {
+                let Ok(number) = value.parse::<usize>() else {
+                    return Err(parse_error(line_number, value_column, format!("'{}' is not a valid number", value)));
+                };
+                if number == 0 || number > MAX_MAZE_SIZE {
+                    return Err(parse_error(line_number, value_column, format!("{} must be between 1 and {}", key.trim(), MAX_MAZE_SIZE)));
+                }
+                if key.trim() == "width" {
+                    width = Some(number);
+                } else {
+                    height = Some(number);
+                }
+            },
+            "player_start" | "pen_exit" | "pen_centre" |
+            "scatter_blinky" | "scatter_pinky" | "scatter_inky" | "scatter_clyde" | "fruit" => {
+                let position = value.split_once(',')
+                    .and_then(|(x, y)| Some(Vec2::new(x.trim().parse().ok()?, y.trim().parse().ok()?)));
+                let Some(position) = position else {
+                    return Err(parse_error(line_number, value_column, format!("'{}' is not a valid position, expected 'x, y'", value)));
+                };
+                position_lines.push((key.trim(), position, line_number, value_column));
+                match key.trim() {
+                    "player_start" => player_start = Some(position),
+                    "pen_exit" => pen_exit = Some(position),
+                    "pen_centre" => pen_centre = Some(position),
+                    "scatter_blinky" => scatter_blinky = Some(position),
+                    "scatter_pinky" => scatter_pinky = Some(position),
+                    "scatter_inky" => scatter_inky = Some(position),
+                    "fruit" => fruit_position = Some(position),
+                    _ => scatter_clyde = Some(position),
+                }
+            },
+            other => {
+                let key_column = line.len() - line.trim_start().len() + 1;
+                return Err(parse_error(line_number, key_column, format!("unknown header key '{}'", other)));
+            },
+        }
+    }
+
+    let Some(width) = width else {
+        return Err(parse_error(last_header_line, 1, "header is missing 'width'"));
+    };
+    let Some(height) = height else {
+        return Err(parse_error(last_header_line, 1, "header is missing 'height'"));
+    };
+    let Some(player_start) = player_start else {
+        return Err(parse_error(last_header_line, 1, "header is missing 'player_start'"));
+    };
+    let Some(pen_exit) = pen_exit else {
+        return Err(parse_error(last_header_line, 1, "header is missing 'pen_exit'"));
+    };
+    let Some(pen_centre) = pen_centre else {
+        return Err(parse_error(last_header_line, 1, "header is missing 'pen_centre'"));
+    };
+
+    // blinky and pinky take the top corners, inky and clyde the bottom ones
+    let last_col = width as f32 - 1.0;
+    let last_row = height as f32 - 1.0;
+    let scatter_corners = ScatterCorners {
+        blinky: scatter_blinky.unwrap_or(Vec2::new(last_col, 0.0)),
+        pinky: scatter_pinky.unwrap_or(Vec2::new(0.0, 0.0)),
+        inky: scatter_inky.unwrap_or(Vec2::new(last_col, last_row)),
+        clyde: scatter_clyde.unwrap_or(Vec2::new(0.0, last_row)),
+    };
+    let fruit_position = fruit_position.unwrap_or(player_start);
+
+    const WALL: BlockCell = BlockCell {block_type: BlockType::Wall, block_reward: BlockReward::Nothing};
+    let mut game_blocks = vec![WALL; width * height];
+
+    // warp tiles found so far for each digit, with the line and column they were found at
+    let mut warps: [Vec<(usize, usize, usize, usize)>; 10] = Default::default();
+
+    let mut row_index = 0;
+    for (line_number, line) in lines {
+        if row_index == height {
+            if line.trim().is_empty() {
+                continue;
+            }
+            return Err(parse_error(line_number, 1, format!("grid has more than {} rows", height)));
+        }
+
+        let mut col_index = 0;
+        for character in line.chars() {
+            let column = col_index + 1;
+            if col_index == width {
+                return Err(parse_error(line_number, column, format!("row is longer than {} columns", width)));
+            }
+
+            game_blocks[row_index * width + col_index] = match character {
+                '#' => WALL,
+                '=' => BlockCell {block_type: BlockType::PenDoor, block_reward: BlockReward::Nothing},
+                '.' => BlockCell {block_type: BlockType::Path, block_reward: BlockReward::PointToken},
+                'o' => BlockCell {block_type: BlockType::Path, block_reward: BlockReward::GhostWeaknessToken},
+                '_' => BlockCell {block_type: BlockType::Path, block_reward: BlockReward::Nothing},
+                '0'..='9' => {
+                    let digit = character as usize - '0' as usize;
+                    warps[digit].push((row_index, col_index, line_number, column));
+                    // the destination is filled in once the partner tile has been found
+                    BlockCell {block_type: BlockType::Warp(0, 0), block_reward: BlockReward::Nothing}
+                },
+                other => return Err(parse_error(line_number, column, format!("unknown maze character '{}'", other))),
+            };
+
+            col_index += 1;
+        }
+
+        if col_index < width {
+            return Err(parse_error(line_number, col_index + 1, format!("row is shorter than {} columns", width)));
+        }
+
+        row_index += 1;
+    }
+
+    if row_index < height {
+        return Err(parse_error(last_header_line + row_index + 1, 1, format!("grid has {} rows but the header gives a height of {}", row_index, height)));
+    }
+
+    // link up each pair of warp tiles
+    for (digit, tiles) in warps.iter().enumerate() {
+        match tiles.as_slice() {
+            [] => {},
+            [(row_a, col_a, ..), (row_b, col_b, ..)] => {
+                game_blocks[row_a * width + col_a].block_type = BlockType::Warp(*col_b as u8, *row_b as u8);
+                game_blocks[row_b * width + col_b].block_type = BlockType::Warp(*col_a as u8, *row_a as u8);
+            },
+            [(_, _, line, column), ..] => {
+                return Err(parse_error(*line, *column, format!("warp tile '{}' must appear exactly twice but appears {} times", digit, tiles.len())));
+            },
+        }
+    }
+
+    // the positions the player and fruit are put at have to be on the path, the pen centre just has to be on the board
+    for (key, position, line, column) in position_lines {
+        if !matches!(key, "player_start" | "pen_exit" | "pen_centre" | "fruit") {
+            continue;
+        }
+        if position.x < 0.0 || position.y < 0.0 || position.x > last_col || position.y > last_row {
+            return Err(parse_error(line, column, format!("{} ({}, {}) is off the {} by {} board", key, position.x, position.y, width, height)));
+        }
+        if key == "pen_centre" {
+            continue;
+        }
+
+        // a position between cells covers the cells on both sides
+        let on_path = [position.x.floor(), position.x.ceil()].into_iter().all(|x| {
+            [position.y.floor(), position.y.ceil()].into_iter().all(|y| {
+                matches!(game_blocks[y as usize * width + x as usize].block_type, BlockType::Path | BlockType::Warp(..))
+            })
+        });
+        if !on_path {
+            return Err(parse_error(line, column, format!("{} ({}, {}) is not on the path", key, position.x, position.y)));
+        }
+    }
+
+    Ok(Maze { name, width, height, game_blocks, player_start, pen_exit, pen_centre, scatter_corners, fruit_position })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // line 8 is the first row of the grid
+    const TEST_MAZE: &str = "name: Test
+width: 5
+height: 5
+player_start: 2, 3
+pen_exit: 2, 1
+pen_centre: 2, 2
+---
+#####
+1...1
+#.#.#
+#o._#
+#####
+";
+
+    fn error_at(source: &str) -> (usize, usize) {
+        let error = parse_maze(source).err().expect("maze should not parse");
+        (error.line, error.column)
+    }
+
+    #[test]
+    fn parses_test_maze() {
+        let maze = parse_maze(TEST_MAZE).unwrap();
+
+        assert_eq!((maze.width, maze.height), (5, 5));
+        assert_eq!(maze.player_start, Vec2::new(2.0, 3.0));
+        assert!(matches!(maze.game_blocks[5].block_type, BlockType::Warp(4, 1)));
+        assert!(matches!(maze.game_blocks[9].block_type, BlockType::Warp(0, 1)));
+        assert!(matches!(maze.game_blocks[16].block_reward, BlockReward::GhostWeaknessToken));
+    }
+
+    #[test]
+    fn bundled_mazes_parse() {
+        parse_maze(include_str!("../assets/levels/classic.maze")).unwrap();
+        parse_maze(include_str!("../assets/levels/testing_easy.maze")).unwrap();
+    }
+
+    #[test]
+    fn short_row() {
+        assert_eq!(error_at(&TEST_MAZE.replace("#.#.#", "#.#")), (10, 4));
+    }
+
+    #[test]
+    fn long_row() {
+        assert_eq!(error_at(&TEST_MAZE.replace("#.#.#", "#.#.##")), (10, 6));
+    }
+
+    #[test]
+    fn unknown_cell_character() {
+        assert_eq!(error_at(&TEST_MAZE.replace("#o._#", "#o.x#")), (11, 4));
+    }
+
+    #[test]
+    fn missing_player_start() {
+        // reported at the separator, which moves up a line
+        assert_eq!(error_at(&TEST_MAZE.replace("player_start: 2, 3\n", "")), (6, 1));
+    }
+
+    #[test]
+    fn missing_pen_exit() {
+        assert_eq!(error_at(&TEST_MAZE.replace("pen_exit: 2, 1\n", "")), (6, 1));
+    }
+
+    #[test]
+    fn player_start_off_the_board() {
+        assert_eq!(error_at(&TEST_MAZE.replace("player_start: 2, 3", "player_start: 2, 5")), (4, 15));
+    }
+
+    #[test]
+    fn player_start_in_a_wall() {
+        assert_eq!(error_at(&TEST_MAZE.replace("player_start: 2, 3", "player_start: 2, 2")), (4, 15));
+    }
+
+    #[test]
+    fn position_between_cells_next_to_a_wall() {
+        assert_eq!(error_at(&TEST_MAZE.replace("pen_exit: 2, 1", "pen_exit: 2, 1.5")), (5, 11));
+        assert_eq!(error_at(&TEST_MAZE.replace("pen_exit: 2, 1", "pen_exit: 2, 1\nfruit: 3.5, 3")), (6, 8));
+    }
+
+    #[test]
+    fn pen_centre_only_needs_to_be_on_the_board() {
+        assert!(parse_maze(TEST_MAZE).is_ok());
+        assert_eq!(error_at(&TEST_MAZE.replace("pen_centre: 2, 2", "pen_centre: -1, 2")), (6, 13));
+    }
+
+    #[test]
+    fn unpaired_warp() {
+        assert_eq!(error_at(&TEST_MAZE.replace("1...1", "1...2")), (9, 1));
+    }
+
+    #[test]
+    fn crlf_line_endings() {
+        let source = TEST_MAZE.replace('\n', "\r\n");
+        let maze = parse_maze(&source).unwrap();
+        assert_eq!((maze.width, maze.height), (5, 5));
+
+        // the carriage returns aren't counted as part of the row
+        assert_eq!(error_at(&source.replace("#o._#", "#o.x#")), (11, 4));
+        assert_eq!(error_at(&source.replace("#.#.#", "#.#")), (10, 4));
+    }
+}
@@ -0,0 +1,213 @@
+use bevy::{prelude::*, render::{render_asset::RenderAssetUsages, render_resource::{Extent3d, TextureDimension, TextureFormat}}};
+
+use crate::gamelogic::{BlockCell, BlockType, GameLogic, OnGameplayScreen, DEFAULT_TILE_SIZE};
+use crate::MultiColoured;
+
+/*
+ * Builds the maze graphics from the gameboard rather than using a pre-drawn background
+ *
+ * Walls are drawn as a double outline wherever a wall cell meets a path, inner corners get rounded off
+ * because the distance to a diagonal path cell is measured to its corner. The outline image is white so
+ * it can be tinted (MultiColoured) and is drawn with a one cell border so the outside edge of the board gets
+ * an outline as well (apart from next to warp tiles, which are left open as tunnels).
+ */
+
+const TILE_PX: usize = DEFAULT_TILE_SIZE as usize;
+
+// distances (in px from the edge of the nearest path cell) that get drawn as part of the outline
+const OUTER_LINE: std::ops::Range<f32> = 0.0..1.0;
+const INNER_LINE: std::ops::Range<f32> = 3.0..4.0;
+
+const PEN_DOOR_COLOUR: Color = Color::srgb(1.0, 0.72, 0.85);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum CellShape {
+    Open,
+    Wall,
+    PenDoor,
+}
+
+/*
+ * What to draw for the given cell - cells outside of the board are walls unless they are next to a warp tile
+ */
+fn cell_shape(game_logic: &GameLogic, col_index: i32, row_index: i32) -> CellShape {
+    match game_logic.get_block(col_index, row_index) {
+        Some(block) => match block.block_type {
+            BlockType::Wall => CellShape::Wall,
+            BlockType::PenDoor => CellShape::PenDoor,
+            BlockType::Path | BlockType::Warp(..) => CellShape::Open,
+        },
+        None => {
+            let next_to_warp = [(-1, 0), (1, 0), (0, -1), (0, 1)].iter().any(|(col_offset, row_offset)| {
+                matches!(game_logic.get_block(col_index + col_offset, row_index + row_offset),
+                    Some(BlockCell { block_type: BlockType::Warp(..), .. }))
+            });
+
+            if next_to_warp { CellShape::Open } else { CellShape::Wall }
+        },
+    }
+}
+
+/*
+ * Draw the wall outlines for the whole board (plus the one cell border) into an image
+ */
+pub fn build_wall_image(game_logic: &GameLogic) -> Image {
+    let image_cols = game_logic.width + 2;
+    let image_rows = game_logic.height + 2;
+    let image_width = image_cols * TILE_PX;
+
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: image_width as u32,
+            height: (image_rows * TILE_PX) as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &[0, 0, 0, 0],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::RENDER_WORLD,
+    );
+
+    for image_row in 0..image_rows {
+        for image_col in 0..image_cols {
+            // the image has an extra cell on every side of the board
+            let col_index = image_col as i32 - 1;
+            let row_index = image_row as i32 - 1;
+
+            if cell_shape(game_logic, col_index, row_index) != CellShape::Wall {
+                continue;
+            }
+
+            // find the neighbouring cells that are open, the outline is drawn along them
+            let mut open_neighbours: Vec<(f32, f32)> = Vec::new();
+            for row_offset in -1..=1 {
+                for col_offset in -1..=1 {
+                    if (col_offset != 0 || row_offset != 0) &&
+                        cell_shape(game_logic, col_index + col_offset, row_index + row_offset) == CellShape::Open {
+                        open_neighbours.push((col_offset as f32, row_offset as f32));
+                    }
+                }
+            }
+
+            if open_neighbours.is_empty() {
+                continue;
+            }
+
+            for pixel_y in 0..TILE_PX {
+                for pixel_x in 0..TILE_PX {
+                    // pixel centre relative to the top left of this cell
+                    let pixel = Vec2::new(pixel_x as f32 + 0.5, pixel_y as f32 + 0.5);
+
+                    let distance = open_neighbours.iter().map(|(col_offset, row_offset)| {
+                        let cell_min = Vec2::new(col_offset * TILE_PX as f32, row_offset * TILE_PX as f32);
+                        let cell_max = cell_min + Vec2::splat(TILE_PX as f32);
+
+                        (cell_min - pixel).max(pixel - cell_max).max(Vec2::ZERO).length()
+                    }).fold(f32::MAX, f32::min);
+
+                    if OUTER_LINE.contains(&distance) || INNER_LINE.contains(&distance) {
+                        let x = (image_col * TILE_PX) + pixel_x;
+                        let y = (image_row * TILE_PX) + pixel_y;
+                        let index = ((y * image_width) + x) * 4;
+                        image.data[index..index + 4].copy_from_slice(&[255, 255, 255, 255]);
+                    }
+                }
+            }
+        }
+    }
+
+    image
+}
+
+/*
+ * Spawn the generated maze graphics - the wall outlines, the pen door and the covers over the warp tunnels
+ */
+pub fn spawn_maze_graphics(
+    commands: &mut Commands,
+    images: &mut Assets<Image>,
+    game_logic: &GameLogic,
+) {
+    let board_centre = game_logic.get_screen_coords(
+        (game_logic.width as f32 - 1.0) / 2.0,
+        (game_logic.height as f32 - 1.0) / 2.0);
+
+    commands.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                // the image includes a border of one cell around the board
+                custom_size: Some(Vec2::new(
+                    (game_logic.width + 2) as f32 * game_logic.tile_size,
+                    (game_logic.height + 2) as f32 * game_logic.tile_size)),
+                color: Color::srgb(0.0, 0.0, 1.0),
+                ..default()
+            },
+            transform: Transform::from_xyz(board_centre.x, board_centre.y, 0.0),
+            texture: images.add(build_wall_image(game_logic)),
+            ..default()
+        },
+        MultiColoured,
+        OnGameplayScreen,
+    ));
+
+    let tile_scale = game_logic.tile_scale();
+
+    for row_index in 0..game_logic.height as i32 {
+        for col_index in 0..game_logic.width as i32 {
+            let screen_pos = game_logic.get_screen_coords(col_index as f32, row_index as f32);
+
+            match cell_shape(game_logic, col_index, row_index) {
+                CellShape::PenDoor => {
+                    // the door is drawn across the side of the cell that opens onto a path
+                    for (col_offset, row_offset) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                        if cell_shape(game_logic, col_index + col_offset, row_index + row_offset) != CellShape::Open {
+                            continue;
+                        }
+
+                        // sit the door between the outer and inner lines of the outline
+                        let inset = (game_logic.tile_size / 2.0) - (2.0 * tile_scale);
+                        let size = if col_offset == 0 {
+                            Vec2::new(game_logic.tile_size, 3.0 * tile_scale)
+                        } else {
+                            Vec2::new(3.0 * tile_scale, game_logic.tile_size)
+                        };
+
+                        commands.spawn((
+                            SpriteBundle {
+                                sprite: Sprite {
+                                    custom_size: Some(size),
+                                    color: PEN_DOOR_COLOUR,
+                                    ..default()
+                                },
+                                transform: Transform::from_xyz(
+                                    screen_pos.x + (col_offset as f32 * inset),
+                                    screen_pos.y - (row_offset as f32 * inset),
+                                    0.0),
+                                ..default()
+                            },
+                            OnGameplayScreen,
+                        ));
+                    }
+                },
+                CellShape::Open => {
+                    if let Some(BlockCell { block_type: BlockType::Warp(..), .. }) = game_logic.get_block(col_index, row_index) {
+                        // cover the warp tile so characters look like they are going into the tunnel
+                        commands.spawn((
+                            SpriteBundle {
+                                sprite: Sprite {
+                                    custom_size: Some(Vec2::splat(game_logic.tile_size)),
+                                    color: Color::BLACK,
+                                    ..default()
+                                },
+                                // this should be above the characters
+                                transform: Transform::from_xyz(screen_pos.x, screen_pos.y, 0.05),
+                                ..default()
+                            },
+                            OnGameplayScreen,
+                        ));
+                    }
+                },
+                CellShape::Wall => {},
+            }
+        }
+    }
+}
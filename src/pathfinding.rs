@@ -0,0 +1,346 @@
+use std::collections::{HashMap, VecDeque};
+
+use bevy::prelude::*;
+
+use crate::gamelogic::{BlockType, Direction, GameLogic};
+
+/*
+ * Pathfinding over the gameboard
+ *
+ * A distance field holds the number of moves from every cell to a target cell, so at any cell the quickest
+ * way to the target is towards whichever neighbouring cell has the smallest distance. Warp tiles count as
+ * being next to the tile they warp to. Distance fields are cached per target cell as the ghosts tend to aim
+ * for the same few cells over and over, only the most recently used ones are kept as each one is the size of the
+ * whole board.
+ */
+
+const UNREACHABLE: u32 = u32::MAX;
+// the most distance fields kept at once
+const CACHE_SIZE: usize = 64;
+
+pub struct DistanceField {
+    width: usize,
+    height: usize,
+    distances: Vec<u32>,
+}
+
+impl DistanceField {
+    /*
+     * Breadth first search outwards from the target cell
+     */
+    pub fn new(game_logic: &GameLogic, target: IVec2) -> Self {
+        let mut distance_field = DistanceField {
+            width: game_logic.width,
+            height: game_logic.height,
+            distances: vec![UNREACHABLE; game_logic.width * game_logic.height],
+        };
+
+        if !is_walkable(game_logic, target) {
+            return distance_field;
+        }
+
+        let mut to_visit = VecDeque::new();
+        distance_field.distances[target.y as usize * game_logic.width + target.x as usize] = 0;
+        to_visit.push_back(target);
+
+        while let Some(cell) = to_visit.pop_front() {
+            let next_distance = distance_field.distances[cell.y as usize * game_logic.width + cell.x as usize] + 1;
+
+            for neighbour in neighbours(game_logic, cell) {
+                let index = neighbour.y as usize * game_logic.width + neighbour.x as usize;
+                if distance_field.distances[index] == UNREACHABLE {
+                    distance_field.distances[index] = next_distance;
+                    to_visit.push_back(neighbour);
+                }
+            }
+        }
+
+        distance_field
+    }
+
+    /*
+     * Number of moves from the given cell to the target, None if the target can't be reached from there
+     */
+    pub fn distance(&self, cell: IVec2) -> Option<u32> {
+        if cell.x < 0 || cell.y < 0 || cell.x as usize >= self.width || cell.y as usize >= self.height {
+            return None;
+        }
+
+        match self.distances[cell.y as usize * self.width + cell.x as usize] {
+            UNREACHABLE => None,
+            distance => Some(distance),
+        }
+    }
+}
+
+fn is_walkable(game_logic: &GameLogic, cell: IVec2) -> bool {
+    matches!(game_logic.get_block(cell.x, cell.y).map(|block| block.block_type), Some(BlockType::Path | BlockType::Warp(..)))
+}
+
+/*
+ * All the cells that can be moved to in one step from the given cell
+ */
+fn neighbours(game_logic: &GameLogic, cell: IVec2) -> Vec<IVec2> {
+    let mut cells: Vec<IVec2> = [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y].iter()
+        .map(|offset| cell + *offset)
+        .filter(|neighbour| is_walkable(game_logic, *neighbour))
+        .collect();
+
+    // moving onto a warp tile takes you straight to the other end
+    if let Some(BlockType::Warp(x, y)) = game_logic.get_block(cell.x, cell.y).map(|block| block.block_type) {
+        cells.push(IVec2::new(x as i32, y as i32));
+    }
+
+    cells
+}
+
+/*
+ * The walkable cell closest to the given position - targets can be inside walls or off the board altogether
+ * Searches in square rings outwards from the position, stopping once the rings are further away than the closest cell
+ * found so far. Cells the same distance away go to the one in the earliest row, then the earliest column.
+ */
+fn nearest_walkable_cell(game_logic: &GameLogic, position: Vec2) -> Option<IVec2> {
+    let rounded = position.round().as_ivec2();
+    if is_walkable(game_logic, rounded) {
+        return Some(rounded);
+    }
+
+    let last_col = game_logic.width as i32 - 1;
+    let last_row = game_logic.height as i32 - 1;
+    // no point looking at the rings that are completely off the board
+    let first_ring = (-rounded.x).max(rounded.x - last_col).max(-rounded.y).max(rounded.y - last_row).max(0);
+    let last_ring = rounded.x.max(last_col - rounded.x).max(rounded.y).max(last_row - rounded.y);
+
+    let mut nearest: Option<(IVec2, f32)> = None;
+    for ring in first_ring..=last_ring {
+        // the position can be up to half a cell from the centre of the rings
+        if let Some((_, nearest_distance)) = nearest {
+            if (ring as f32 - 0.5).powi(2) > nearest_distance {
+                break;
+            }
+        }
+
+        for row_index in (rounded.y - ring).max(0)..=(rounded.y + ring).min(last_row) {
+            let whole_row = (row_index - rounded.y).abs() == ring;
+            for col_index in (rounded.x - ring).max(0)..=(rounded.x + ring).min(last_col) {
+                // only the edge of the ring
+                if !whole_row && (col_index - rounded.x).abs() != ring {
+                    continue;
+                }
+
+                let cell = IVec2::new(col_index, row_index);
+                if !is_walkable(game_logic, cell) {
+                    continue;
+                }
+
+                let distance = cell.as_vec2().distance_squared(position);
+                let closer = match nearest {
+                    None => true,
+                    Some((nearest_cell, nearest_distance)) => distance < nearest_distance ||
+                        (distance == nearest_distance && (cell.y, cell.x) < (nearest_cell.y, nearest_cell.x)),
+                };
+                if closer {
+                    nearest = Some((cell, distance));
+                }
+            }
+        }
+    }
+
+    nearest.map(|(cell, _)| cell)
+}
+
+#[derive(Default)]
+pub struct Pathfinder {
+    // distance fields by the target cell they lead to, along with when they were last used
+    cache: HashMap<IVec2, (DistanceField, u64)>,
+    uses: u64,
+}
+
+impl Pathfinder {
+    /*
+     * Forget all the cached distance fields - needs to happen whenever the gameboard changes
+     */
+    pub fn clear(&mut self) {
+        self.cache.clear();
+    }
+
+    /*
+     * Get the distance field that leads to the walkable cell nearest the target position
+     */
+    pub fn distance_field(&mut self, game_logic: &GameLogic, target: Vec2) -> Option<&DistanceField> {
+        let target_cell = nearest_walkable_cell(game_logic, target)?;
+        self.uses += 1;
+
+        // make room by forgetting the field that has gone unused the longest
+        if self.cache.len() >= CACHE_SIZE && !self.cache.contains_key(&target_cell) {
+            if let Some(oldest) = self.cache.iter().min_by_key(|(_, (_, last_used))| *last_used).map(|(cell, _)| *cell) {
+                self.cache.remove(&oldest);
+            }
+        }
+
+        let (distance_field, last_used) = self.cache.entry(target_cell)
+            .or_insert_with(|| (DistanceField::new(game_logic, target_cell), 0));
+        *last_used = self.uses;
+
+        Some(distance_field)
+    }
+
+    /*
+     * Pick which of the available directions from the current position is on the shortest route to the target
+     * Returns the index of the direction, or None if none of them lead to the target
+     */
+    pub fn best_direction(&mut self, game_logic: &GameLogic, current_pos: Vec2, target: Vec2, available_directions: &[Direction]) -> Option<usize> {
+        let distance_field = self.distance_field(game_logic, target)?;
+        let current_cell = current_pos.round().as_ivec2();
+
+        available_directions.iter().enumerate()
+            .filter_map(|(index, direction)| {
+                let next_cell = current_cell + IVec2::new(direction.horizontal as i32, direction.vertical as i32);
+                distance_field.distance(next_cell).map(|distance| (index, distance))
+            })
+            .min_by_key(|(_, distance)| *distance)
+            .map(|(index, _)| index)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gamelogic::{Horizontal, Vertical};
+    use crate::maze::parse_maze;
+
+    const UP: Direction = Direction { vertical: Vertical::Up, horizontal: Horizontal::Zero };
+    const LEFT: Direction = Direction { vertical: Vertical::Zero, horizontal: Horizontal::Left };
+    const RIGHT: Direction = Direction { vertical: Vertical::Zero, horizontal: Horizontal::Right };
+
+    // a loop along the top with a warp joining the two ends of the bottom row
+    const WARP_MAZE: &str = "width: 7
+height: 4
+player_start: 1, 1
+pen_exit: 3, 1
+pen_centre: 3, 2
+---
+#######
+#_____#
+1_###_1
+#######
+";
+
+    fn game_logic(source: &str) -> GameLogic {
+        GameLogic::from_maze(&parse_maze(source).unwrap())
+    }
+
+    fn open_room(size: usize) -> GameLogic {
+        let mut source = format!("width: {size}\nheight: {size}\nplayer_start: 1, 1\npen_exit: 1, 1\npen_centre: 1, 1\n---\n");
+        source.push_str(&"#".repeat(size));
+        source.push('\n');
+        for _ in 0..size - 2 {
+            source.push_str(&format!("#{}#\n", "_".repeat(size - 2)));
+        }
+        source.push_str(&"#".repeat(size));
+        source.push('\n');
+        game_logic(&source)
+    }
+
+    #[test]
+    fn distance_field() {
+        let game_logic = game_logic(WARP_MAZE);
+        let distance_field = DistanceField::new(&game_logic, IVec2::new(1, 1));
+
+        assert_eq!(distance_field.distance(IVec2::new(1, 1)), Some(0));
+        assert_eq!(distance_field.distance(IVec2::new(5, 1)), Some(4));
+        // walls and cells off the board can't be reached
+        assert_eq!(distance_field.distance(IVec2::new(3, 2)), None);
+        assert_eq!(distance_field.distance(IVec2::new(-1, 1)), None);
+        assert_eq!(distance_field.distance(IVec2::new(7, 1)), None);
+    }
+
+    #[test]
+    fn distance_field_into_a_wall_is_empty() {
+        let game_logic = game_logic(WARP_MAZE);
+        let distance_field = DistanceField::new(&game_logic, IVec2::new(3, 2));
+
+        assert_eq!(distance_field.distance(IVec2::new(1, 1)), None);
+    }
+
+    #[test]
+    fn shortest_route_goes_through_the_warp() {
+        let game_logic = game_logic(WARP_MAZE);
+        let distance_field = DistanceField::new(&game_logic, IVec2::new(5, 2));
+
+        // round the top would be 6 moves
+        assert_eq!(distance_field.distance(IVec2::new(1, 2)), Some(3));
+        assert_eq!(distance_field.distance(IVec2::new(0, 2)), Some(2));
+        assert_eq!(distance_field.distance(IVec2::new(6, 2)), Some(1));
+    }
+
+    #[test]
+    fn best_direction() {
+        let game_logic = game_logic(WARP_MAZE);
+        let mut pathfinder = Pathfinder::default();
+
+        // left into the warp is quicker than going up and round
+        assert_eq!(pathfinder.best_direction(&game_logic, Vec2::new(1.0, 2.0), Vec2::new(5.0, 2.0), &[UP, LEFT]), Some(1));
+        assert_eq!(pathfinder.best_direction(&game_logic, Vec2::new(3.0, 1.0), Vec2::new(5.0, 1.0), &[LEFT, RIGHT]), Some(1));
+        // a target in the wall heads for the nearest path
+        assert_eq!(pathfinder.best_direction(&game_logic, Vec2::new(3.0, 1.0), Vec2::new(1.2, 3.0), &[LEFT, RIGHT]), Some(0));
+        assert_eq!(pathfinder.best_direction(&game_logic, Vec2::new(3.0, 1.0), Vec2::new(5.0, 1.0), &[]), None);
+    }
+
+    // the nearest walkable cell found by checking every cell on the board
+    fn nearest_by_scanning(game_logic: &GameLogic, position: Vec2) -> Option<IVec2> {
+        let rounded = position.round().as_ivec2();
+        if is_walkable(game_logic, rounded) {
+            return Some(rounded);
+        }
+
+        let mut nearest: Option<(IVec2, f32)> = None;
+        for row_index in 0..game_logic.height as i32 {
+            for col_index in 0..game_logic.width as i32 {
+                let cell = IVec2::new(col_index, row_index);
+                if !is_walkable(game_logic, cell) {
+                    continue;
+                }
+
+                let distance = cell.as_vec2().distance_squared(position);
+                match nearest {
+                    Some((_, nearest_distance)) if distance >= nearest_distance => {},
+                    _ => nearest = Some((cell, distance)),
+                }
+            }
+        }
+        nearest.map(|(cell, _)| cell)
+    }
+
+    #[test]
+    fn nearest_walkable_cell_matches_scanning() {
+        for game_logic in [game_logic(WARP_MAZE), open_room(10)] {
+            for y in -24..48 {
+                for x in -24..48 {
+                    let position = Vec2::new(x as f32 * 0.25, y as f32 * 0.25);
+                    assert_eq!(nearest_walkable_cell(&game_logic, position), nearest_by_scanning(&game_logic, position), "at {}", position);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn cache_keeps_the_most_recently_used_fields() {
+        let game_logic = open_room(12);
+        let mut pathfinder = Pathfinder::default();
+
+        pathfinder.distance_field(&game_logic, Vec2::new(1.0, 1.0));
+        for row_index in 1..11 {
+            for col_index in 1..11 {
+                pathfinder.distance_field(&game_logic, Vec2::new(col_index as f32, row_index as f32));
+                // keep using the first one so it doesn't get forgotten
+                pathfinder.distance_field(&game_logic, Vec2::new(1.0, 1.0));
+            }
+        }
+
+        assert_eq!(pathfinder.cache.len(), CACHE_SIZE);
+        assert!(pathfinder.cache.contains_key(&IVec2::new(1, 1)));
+        assert!(pathfinder.cache.contains_key(&IVec2::new(10, 10)));
+        assert!(!pathfinder.cache.contains_key(&IVec2::new(2, 1)));
+    }
+}
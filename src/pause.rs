@@ -0,0 +1,232 @@
+use bevy::prelude::*;
+
+use crate::gamelogic::{GameSimulation, RestartLevel};
+use crate::gamestates::{despawn_screen, GameState};
+use crate::inputactions::{ActionInput, InputAction};
+use crate::players::Players;
+use crate::{LivesLeft, Score};
+
+/*
+ * Pausing
+ *
+ * Escape, P or the gamepad start button pauses the game while it is being played (including the count in before play and the lose life
+ * animation). Pausing stops virtual time, so nothing that runs off the game clock moves on - the simulation and
+ * everything in it, the start delay, the lose life timer and the sprite animations. The pause menu can resume,
+ * restart the level from the beginning or quit back to the main menu. The level can't be restarted while a life is
+ * being lost, as that would get the life back.
+ */
+
+#[derive(Clone, Copy, Default, Eq, PartialEq, Debug, Hash, States)]
+pub enum PauseState {
+    #[default]
+    Running,
+    Paused,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum PauseMenuItem {
+    Resume,
+    RestartLevel,
+    Quit,
+}
+
+const PAUSE_MENU_ITEMS: [PauseMenuItem; 3] = [PauseMenuItem::Resume, PauseMenuItem::RestartLevel, PauseMenuItem::Quit];
+
+impl PauseMenuItem {
+    fn label(&self) -> &'static str {
+        match self {
+            PauseMenuItem::Resume => "Resume",
+            PauseMenuItem::RestartLevel => "Restart level",
+            PauseMenuItem::Quit => "Quit",
+        }
+    }
+
+    fn available(&self, game_state: GameState) -> bool {
+        *self != PauseMenuItem::RestartLevel || game_state != GameState::LoseLife
+    }
+}
+
+const SELECTED_COLOUR: Color = Color::srgb(1.0, 1.0, 0.0);
+const UNAVAILABLE_COLOUR: Color = Color::srgb(0.4, 0.4, 0.4);
+
+#[derive(Component)]
+struct OnPauseScreen;
+
+#[derive(Component)]
+struct PauseMenuText(usize);
+
+// the highlighted item in the pause menu
+#[derive(Resource, Default)]
+struct PauseMenuSelection(usize);
+
+pub struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<PauseState>()
+            .init_resource::<PauseMenuSelection>()
+            .add_systems(Update, toggle_pause.run_if(
+                in_state(GameState::GameStart).or_else(in_state(GameState::Gameplay)).or_else(in_state(GameState::LoseLife))))
+            .add_systems(Update, (pause_menu_input, update_pause_menu).chain().run_if(in_state(PauseState::Paused)))
+            .add_systems(OnEnter(PauseState::Paused), (pause_time, pause_menu_setup))
+            .add_systems(OnExit(PauseState::Paused), (resume_time, despawn_screen::<OnPauseScreen>));
+    }
+}
+
+fn toggle_pause(
+    input: ActionInput,
+    pause_state: Res<State<PauseState>>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+) {
+    if !input.just_pressed(InputAction::Pause) {
+        return;
+    }
+
+    next_pause_state.set(match pause_state.get() {
+        PauseState::Running => PauseState::Paused,
+        PauseState::Paused => PauseState::Running,
+    });
+}
+
+fn pause_time(mut time: ResMut<Time<Virtual>>) {
+    time.pause();
+}
+
+fn resume_time(mut time: ResMut<Time<Virtual>>) {
+    time.unpause();
+}
+
+fn pause_menu_setup(
+    mut commands: Commands,
+    mut selection: ResMut<PauseMenuSelection>,
+) {
+    selection.0 = 0;
+
+    commands.spawn(
+        (
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    flex_direction: FlexDirection::Column,
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    row_gap: Val::Px(10.0),
+                    ..default()
+                },
+                // the game stays visible underneath
+                background_color: Color::srgba(0.0, 0.0, 0.0, 0.7).into(),
+                z_index: ZIndex::Global(10),
+                ..default()
+            },
+            OnPauseScreen,
+        )
+    ).with_children(|commands| {
+        commands.spawn(
+            TextBundle {
+                text: Text::from_section(
+                    "Paused\n",
+                    TextStyle {
+                        font_size: 24.0,
+                        ..default()
+                    }
+                ).with_justify(JustifyText::Center),
+                ..default()
+            }
+        );
+
+        for (index, item) in PAUSE_MENU_ITEMS.iter().enumerate() {
+            commands.spawn((
+                TextBundle {
+                    text: Text::from_section(
+                        item.label(),
+                        TextStyle {
+                            font_size: 20.0,
+                            ..default()
+                        }
+                    ),
+                    ..default()
+                },
+                PauseMenuText(index),
+            ));
+        }
+    });
+}
+
+#[allow(clippy::too_many_arguments)]
+fn pause_menu_input(
+    mut commands: Commands,
+    input: ActionInput,
+    mut selection: ResMut<PauseMenuSelection>,
+    mut next_pause_state: ResMut<NextState<PauseState>>,
+    current_game_state: Res<State<GameState>>,
+    mut game_state: ResMut<NextState<GameState>>,
+    simulation: Option<ResMut<GameSimulation>>,
+    mut score: ResMut<Score>,
+    mut lives_left: ResMut<LivesLeft>,
+    mut players: ResMut<Players>,
+) {
+    let count = PAUSE_MENU_ITEMS.len();
+    let step = if input.just_pressed(InputAction::Up) {
+        count - 1
+    } else if input.just_pressed(InputAction::Down) {
+        1
+    } else {
+        0
+    };
+    if step != 0 {
+        // skip over anything that can't be picked right now
+        selection.0 = (selection.0 + step) % count;
+        while !PAUSE_MENU_ITEMS[selection.0].available(*current_game_state.get()) {
+            selection.0 = (selection.0 + step) % count;
+        }
+    }
+
+    if !input.just_pressed(InputAction::Confirm) || !PAUSE_MENU_ITEMS[selection.0].available(*current_game_state.get()) {
+        return;
+    }
+
+    match PAUSE_MENU_ITEMS[selection.0] {
+        PauseMenuItem::Resume => {},
+        PauseMenuItem::RestartLevel => {
+            // put the score and tokens back to how they were at the start of the level and set it all up again
+            if let Some(mut simulation) = simulation {
+                simulation.restart_level();
+                score.0 = players.restore_scores(&simulation);
+            }
+            commands.insert_resource(RestartLevel);
+            game_state.set(GameState::LevelSetup);
+        },
+        PauseMenuItem::Quit => {
+            // no lives left makes the next game a new one
+            lives_left.0 = 0;
+            score.0 = 0;
+            game_state.set(GameState::MainMenu);
+        },
+    }
+
+    next_pause_state.set(PauseState::Running);
+}
+
+fn update_pause_menu(
+    selection: Res<PauseMenuSelection>,
+    game_state: Res<State<GameState>>,
+    mut texts: Query<(&PauseMenuText, &mut Text)>,
+) {
+    for (menu_text, mut text) in &mut texts {
+        let item = PAUSE_MENU_ITEMS[menu_text.0];
+
+        text.sections[0].value = if menu_text.0 == selection.0 {
+            format!("> {} <", item.label())
+        } else {
+            item.label().to_string()
+        };
+        text.sections[0].style.color = if !item.available(*game_state.get()) {
+            UNAVAILABLE_COLOUR
+        } else if menu_text.0 == selection.0 {
+            SELECTED_COLOUR
+        } else {
+            Color::WHITE
+        };
+    }
+}
@@ -0,0 +1,230 @@
+use bevy::prelude::*;
+
+use crate::extralife::ExtraLivesAwarded;
+use crate::gamelogic::GameSimulation;
+use crate::gamestates::{despawn_screen, GameState};
+use crate::simulation::Simulation;
+use crate::{Level, LivesLeft, Score};
+
+/*
+ * Two player games
+ *
+ * In the alternating game the players take it in turns, swapping over each time one of them loses a life (as long as
+ * the other one still has lives left). Each player has their own simulation - so their own score, level, fruit and
+ * tokens left on the board - and their own lives. Whoever is playing uses the usual game resources, the other player
+ * waits in here until it is their turn again.
+ *
+ * In co-op both players are on the board at once in the same simulation and share the lives. They either share the
+ * score as well, in which case it is a one player game as far as the score goes, or have their own - player one's
+ * is the usual score and player two's is kept in here.
+ */
+
+/*
+ * Everything kept for the player that isn't playing at the moment
+ */
+struct WaitingPlayer {
+    simulation: Simulation,
+    score: i32,
+    lives: i32,
+    level: u32,
+    extra_lives_awarded: i32,
+}
+
+#[derive(Resource)]
+pub struct Players {
+    pub count: usize,
+    // the player playing now, starting from 0
+    pub current: usize,
+    waiting: Option<WaitingPlayer>,
+    // player two's score in co-op, when the players are scored separately
+    pub partner_score: Option<i32>,
+}
+
+impl Default for Players {
+    fn default() -> Self {
+        Players { count: 1, current: 0, waiting: None, partner_score: None }
+    }
+}
+
+impl Players {
+    pub fn one_player() -> Self {
+        Players::default()
+    }
+
+    /*
+     * A two player game, player one goes first and player two waits with their own simulation
+     */
+    pub fn two_players(second_simulation: Simulation, lives: i32) -> Self {
+        Players {
+            count: 2,
+            current: 0,
+            waiting: Some(WaitingPlayer {
+                simulation: second_simulation,
+                score: 0,
+                lives,
+                level: 1,
+                extra_lives_awarded: 0,
+            }),
+            partner_score: None,
+        }
+    }
+
+    /*
+     * Both players playing at once, with a score each or one between them
+     */
+    pub fn co_op(split_scores: bool) -> Self {
+        Players {
+            count: if split_scores { 2 } else { 1 },
+            current: 0,
+            waiting: None,
+            partner_score: split_scores.then_some(0),
+        }
+    }
+
+    /*
+     * True if the players are taking it in turns
+     */
+    pub fn alternating(&self) -> bool {
+        self.waiting.is_some()
+    }
+
+    /*
+     * True if the other player has lives left to take over with
+     */
+    pub fn other_player_can_play(&self) -> bool {
+        self.waiting.as_ref().is_some_and(|waiting| waiting.lives > 0)
+    }
+
+    /*
+     * Every player's score, in player order, given the score of the player playing now
+     */
+    pub fn scores(&self, current_score: i32) -> Vec<i32> {
+        let mut scores = vec![current_score];
+
+        if let Some(waiting) = &self.waiting {
+            scores.insert(if self.current == 0 { 1 } else { 0 }, waiting.score);
+        }
+        if let Some(partner_score) = self.partner_score {
+            scores.push(partner_score);
+        }
+
+        scores
+    }
+
+    /*
+     * Add points scored by one of the players on the board to their score
+     */
+    pub fn add_points(&mut self, score: &mut Score, player: usize, points: i32) {
+        match &mut self.partner_score {
+            Some(partner_score) if player == 1 => *partner_score += points,
+            _ => score.0 += points,
+        }
+    }
+
+    /*
+     * Player one's score (or the shared score) from the simulation, after its scores have been put back to the start
+     * of the level - player two's is put back here as well when they have their own
+     */
+    pub fn restore_scores(&mut self, simulation: &Simulation) -> i32 {
+        match &mut self.partner_score {
+            Some(partner_score) => {
+                *partner_score = simulation.players.get(1).map_or(0, |player| player.score);
+                simulation.players[0].score
+            },
+            None => simulation.score,
+        }
+    }
+}
+
+/*
+ * Set while the level is being set up for the other player to take their turn
+ */
+#[derive(Resource)]
+pub struct SwapPlayers;
+
+#[derive(Component)]
+struct OnPlayerUpScreen;
+
+pub struct PlayersPlugin;
+
+impl Plugin for PlayersPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Players>()
+            .add_systems(OnEnter(GameState::GameStart), show_player_up)
+            .add_systems(OnExit(GameState::GameStart), despawn_screen::<OnPlayerUpScreen>);
+    }
+}
+
+/*
+ * Put the player that is playing aside and bring in the one that was waiting
+ */
+pub fn swap_players(
+    mut players: ResMut<Players>,
+    mut simulation: ResMut<GameSimulation>,
+    mut score: ResMut<Score>,
+    mut lives_left: ResMut<LivesLeft>,
+    mut level: ResMut<Level>,
+    mut extra_lives_awarded: ResMut<ExtraLivesAwarded>,
+) {
+    let Some(incoming) = players.waiting.take() else {
+        return;
+    };
+
+    players.waiting = Some(WaitingPlayer {
+        simulation: std::mem::replace(&mut simulation.0, incoming.simulation),
+        score: score.0,
+        lives: lives_left.0,
+        level: level.0,
+        extra_lives_awarded: extra_lives_awarded.0,
+    });
+    players.current = 1 - players.current;
+
+    score.0 = incoming.score;
+    lives_left.0 = incoming.lives;
+    level.0 = incoming.level;
+    extra_lives_awarded.0 = incoming.extra_lives_awarded;
+
+    info!("Player {} up", players.current + 1);
+}
+
+/*
+ * Say whose turn it is before play starts in an alternating game
+ */
+fn show_player_up(
+    mut commands: Commands,
+    players: Res<Players>,
+) {
+    if !players.alternating() {
+        return;
+    }
+
+    commands.spawn(
+        (
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..default()
+                },
+                ..default()
+            },
+            OnPlayerUpScreen,
+        )
+    ).with_children(|commands| {
+        commands.spawn(
+            TextBundle {
+                text: Text::from_section(
+                    format!("PLAYER {}", if players.current == 0 { "ONE" } else { "TWO" }),
+                    TextStyle {
+                        font_size: 24.0,
+                        color: Color::srgb(0.0, 1.0, 1.0),
+                        ..default()
+                    }
+                ).with_justify(JustifyText::Center),
+                ..default()
+            }
+        );
+    });
+}
@@ -0,0 +1,481 @@
+use std::{fmt, fs};
+
+use bevy::prelude::*;
+
+use crate::fixedtimestep::SimulationSet;
+use crate::gamelogic::{read_player_input, Direction, GameSimulation, GhostComboPoints, GhostFrightenedBehaviour, Horizontal, TickInput, Vertical};
+use crate::ghost::FrightenedBehaviour;
+use crate::gamerng::GameSeed;
+use crate::gamestates::GameState;
+use crate::levels::Difficulty;
+use crate::settings::{on_off, parse_on_off};
+use crate::simulation::{MovementOptions, Scoring};
+use crate::maze::MazePath;
+use crate::players::Players;
+
+/*
+ * Replays
+ *
+ * Every game is recorded to last_game.replay: the seed, the maze and the direction held down on every tick of the
+ * simulation. Starting the game with "--replay <file>" feeds the recorded directions back in instead of the keyboard
+ * (until they run out), which plays the game out exactly the same again.
+ *
+ * Replay files are plain text, a header of "key: value" lines, a "---" separator and then the ticks. The header has
+ * the seed, the maze and anything else that changes how the game plays out (the rest are optional and default to how
+ * the game played before they were added - wander, normal, the input buffer and cornering off and the usual ghost
+ * combo points). Each line holds
+ * a second of ticks, one character each laid out like a number pad (8 is up, 4 left, 5 nothing held down, 9 up and
+ * right...) followed by the checksum of the simulation after the last tick on the line.
+ *
+ *  seed: 1234
+ *  maze: levels/classic.maze
+ *  frightened: wander
+ *  difficulty: normal
+ *  input_buffer: on
+ *  cornering: off
+ *  ghost_combo: 200 400 800 1600
+ *  ---
+ *  555555555555555555555555555555444444444444444444444444444444 8f0d21a3c65b7e19
+ */
+
+const RECORDING_FILE: &str = "last_game.replay";
+
+const TICKS_PER_LINE: usize = 60;
+
+pub struct ReplayTick {
+    pub input: Direction,
+    // only known for the last tick of each line when the replay has been read from a file
+    pub checksum: Option<u64>,
+}
+
+pub struct Replay {
+    pub seed: u64,
+    pub maze: String,
+    pub frightened: FrightenedBehaviour,
+    pub difficulty: Difficulty,
+    pub movement: MovementOptions,
+    // points for each ghost eaten in a row
+    pub ghost_combo: Vec<i32>,
+    pub ticks: Vec<ReplayTick>,
+}
+
+impl Default for Replay {
+    fn default() -> Self {
+        Replay {
+            seed: 0,
+            maze: String::new(),
+            frightened: FrightenedBehaviour::default(),
+            difficulty: Difficulty::default(),
+            movement: MovementOptions::default(),
+            ghost_combo: Scoring::default().ghost_combo,
+            ticks: Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct ReplayParseError {
+    pub line: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ReplayParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+fn input_to_char(input: Direction) -> char {
+    let row = match input.vertical {
+        Vertical::Up => ['7', '8', '9'],
+        Vertical::Zero => ['4', '5', '6'],
+        Vertical::Down => ['1', '2', '3'],
+    };
+
+    match input.horizontal {
+        Horizontal::Left => row[0],
+        Horizontal::Zero => row[1],
+        Horizontal::Right => row[2],
+    }
+}
+
+fn char_to_input(character: char) -> Option<Direction> {
+    let digit = character.to_digit(10).filter(|digit| *digit >= 1)?;
+
+    let vertical = match (digit - 1) / 3 {
+        0 => Vertical::Down,
+        1 => Vertical::Zero,
+        _ => Vertical::Up,
+    };
+    let horizontal = match (digit - 1) % 3 {
+        0 => Horizontal::Left,
+        1 => Horizontal::Zero,
+        _ => Horizontal::Right,
+    };
+
+    Some(Direction { vertical, horizontal })
+}
+
+impl Replay {
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "seed: {}\nmaze: {}\nfrightened: {}\ndifficulty: {}\ninput_buffer: {}\ncornering: {}\nghost_combo: {}\n---\n",
+            self.seed, self.maze, self.frightened.name(), self.difficulty.name(),
+            on_off(self.movement.input_buffer), on_off(self.movement.cornering),
+            Scoring::ghost_combo_text(&self.ghost_combo));
+
+        for line in self.ticks.chunks(TICKS_PER_LINE) {
+            text.extend(line.iter().map(|tick| input_to_char(tick.input)));
+
+            if let Some(checksum) = line.last().and_then(|tick| tick.checksum) {
+                text.push_str(&format!(" {:016x}", checksum));
+            }
+            text.push('\n');
+        }
+
+        text
+    }
+
+    pub fn parse(source: &str) -> Result<Replay, ReplayParseError> {
+        let mut replay = Replay::default();
+        let mut lines = source.lines().enumerate().map(|(index, line)| (index + 1, line));
+
+        let mut seed = None;
+        let mut maze = None;
+        // anything missing from the header is reported at the end of it
+        let mut last_header_line = 1;
+
+        // header
+        for (line_number, line) in lines.by_ref() {
+            last_header_line = line_number;
+            let line = line.trim();
+            if line == "---" {
+                break;
+            }
+            if line.is_empty() || line.starts_with(';') {
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once(':') else {
+                return Err(ReplayParseError { line: line_number, message: format!("expected \"key: value\", found \"{}\"", line) });
+            };
+
+            match key.trim() {
+                "seed" => seed = Some(value.trim().parse().map_err(|_| ReplayParseError {
+                    line: line_number,
+                    message: format!("invalid seed \"{}\"", value.trim()),
+                })?),
+                "maze" => maze = Some(value.trim().to_string()),
+                "frightened" => replay.frightened = FrightenedBehaviour::from_name(value.trim()).ok_or_else(|| ReplayParseError {
+                    line: line_number,
+                    message: format!("invalid frightened behaviour \"{}\"", value.trim()),
+                })?,
+                "difficulty" => replay.difficulty = Difficulty::from_name(value.trim()).ok_or_else(|| ReplayParseError {
+                    line: line_number,
+                    message: format!("invalid difficulty \"{}\"", value.trim()),
+                })?,
+                "input_buffer" => replay.movement.input_buffer = parse_on_off(value.trim()).ok_or_else(|| ReplayParseError {
+                    line: line_number,
+                    message: format!("invalid input_buffer \"{}\"", value.trim()),
+                })?,
+                "cornering" => replay.movement.cornering = parse_on_off(value.trim()).ok_or_else(|| ReplayParseError {
+                    line: line_number,
+                    message: format!("invalid cornering \"{}\"", value.trim()),
+                })?,
+                "ghost_combo" => replay.ghost_combo = Scoring::parse_ghost_combo(value.trim()).ok_or_else(|| ReplayParseError {
+                    line: line_number,
+                    message: format!("invalid ghost_combo \"{}\"", value.trim()),
+                })?,
+                key => return Err(ReplayParseError { line: line_number, message: format!("unknown key \"{}\"", key) }),
+            }
+        }
+
+        replay.seed = seed.ok_or(ReplayParseError { line: last_header_line, message: String::from("missing seed") })?;
+        replay.maze = maze.ok_or(ReplayParseError { line: last_header_line, message: String::from("missing maze") })?;
+
+        // ticks
+        for (line_number, line) in lines {
+            let mut parts = line.split_whitespace();
+            let Some(inputs) = parts.next() else {
+                continue;
+            };
+
+            for character in inputs.chars() {
+                let input = char_to_input(character).ok_or_else(|| ReplayParseError {
+                    line: line_number,
+                    message: format!("invalid input \"{}\"", character),
+                })?;
+                replay.ticks.push(ReplayTick { input, checksum: None });
+            }
+
+            if let Some(checksum) = parts.next() {
+                let checksum = u64::from_str_radix(checksum, 16).map_err(|_| ReplayParseError {
+                    line: line_number,
+                    message: format!("invalid checksum \"{}\"", checksum),
+                })?;
+
+                if let Some(last_tick) = replay.ticks.last_mut() {
+                    last_tick.checksum = Some(checksum);
+                }
+            }
+        }
+
+        Ok(replay)
+    }
+}
+
+/*
+ * The game being recorded
+ */
+#[derive(Resource, Default)]
+struct ReplayRecorder(Replay);
+
+/*
+ * The replay being played back
+ */
+#[derive(Resource)]
+pub struct ReplayPlayback {
+    replay: Replay,
+    finished: bool,
+    desynced: bool,
+}
+
+impl ReplayPlayback {
+    // the game being played back has to be set up the same way it was recorded
+    pub fn difficulty(&self) -> Difficulty {
+        self.replay.difficulty
+    }
+
+    pub fn movement(&self) -> MovementOptions {
+        self.replay.movement
+    }
+}
+
+fn replay_path_from_args() -> Option<String> {
+    let mut args = std::env::args();
+    while let Some(arg) = args.next() {
+        if arg == "--replay" {
+            return args.next();
+        }
+        if let Some(path) = arg.strip_prefix("--replay=") {
+            return Some(path.to_string());
+        }
+    }
+    None
+}
+
+pub struct ReplayPlugin;
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ReplayRecorder>()
+            .add_systems(FixedUpdate, play_back_input.in_set(SimulationSet::Input).after(read_player_input).run_if(in_state(GameState::Gameplay)))
+            .add_systems(FixedUpdate, record_tick.after(SimulationSet::Simulate).run_if(in_state(GameState::Gameplay)))
+            .add_systems(OnExit(GameState::Gameplay), save_recording);
+
+        let Some(path) = replay_path_from_args() else {
+            return;
+        };
+
+        let replay = match fs::read_to_string(&path) {
+            Ok(source) => match Replay::parse(&source) {
+                Ok(replay) => replay,
+                Err(error) => {
+                    error!("Invalid replay file {}: {}", path, error);
+                    return;
+                },
+            },
+            Err(error) => {
+                error!("Could not read replay file {}: {}", path, error);
+                return;
+            },
+        };
+        info!("Playing back replay {} ({} ticks)", path, replay.ticks.len());
+
+        // play the game the replay was recorded with
+        app.insert_resource(GameSeed(replay.seed))
+            .insert_resource(MazePath(replay.maze.clone()))
+            .insert_resource(GhostFrightenedBehaviour(replay.frightened))
+            .insert_resource(GhostComboPoints(replay.ghost_combo.clone()))
+            .insert_resource(ReplayPlayback { replay, finished: false, desynced: false });
+    }
+}
+
+/*
+ * Use the recorded input for the next tick instead of the keyboard
+ */
+fn play_back_input(
+    playback: Option<ResMut<ReplayPlayback>>,
+    simulation: Res<GameSimulation>,
+    mut tick_input: ResMut<TickInput>,
+) {
+    let Some(mut playback) = playback else {
+        return;
+    };
+    if playback.finished {
+        return;
+    }
+
+    match playback.replay.ticks.get(simulation.ticks) {
+        Some(tick) => tick_input.0 = tick.input,
+        None => {
+            info!("Replay finished after {} ticks", simulation.ticks);
+            playback.finished = true;
+        },
+    }
+}
+
+/*
+ * Record the tick that has just happened, or check it against the replay when playing one back
+ */
+fn record_tick(
+    simulation: Res<GameSimulation>,
+    tick_input: Res<TickInput>,
+    mut recorder: ResMut<ReplayRecorder>,
+    playback: Option<ResMut<ReplayPlayback>>,
+    players: Res<Players>,
+) {
+    if let Some(mut playback) = playback {
+        if playback.finished || playback.desynced || simulation.ticks == 0 {
+            return;
+        }
+        if simulation.restarted {
+            info!("Level restarted, stopping the replay");
+            playback.finished = true;
+            return;
+        }
+
+        let checksum = simulation.checksum();
+        if let Some(expected) = playback.replay.ticks.get(simulation.ticks - 1).and_then(|tick| tick.checksum) {
+            if checksum != expected {
+                error!("Replay desync at tick {}: checksum {:016x}, expected {:016x}", simulation.ticks, checksum, expected);
+                playback.desynced = true;
+            }
+        }
+        return;
+    }
+
+    // a new game has started
+    if simulation.is_added() {
+        recorder.0.ticks.clear();
+        recorder.0.difficulty = simulation.difficulty;
+        recorder.0.movement = simulation.movement;
+        recorder.0.ghost_combo = simulation.scoring.ghost_combo.clone();
+    }
+
+    // two player games swap between two simulations or need both players' input, so they aren't recorded
+    if players.count > 1 || simulation.players.len() > 1 || simulation.human_ghost.is_some() {
+        return;
+    }
+
+    // the simulation doesn't tick once the player has been caught or the level cleared, even if this system still runs
+    // and a game with a restarted level can't be played back, so the recording stops where the restart happened
+    if recorder.0.ticks.len() < simulation.ticks && !simulation.restarted {
+        recorder.0.ticks.push(ReplayTick { input: tick_input.0, checksum: Some(simulation.checksum()) });
+    }
+}
+
+fn save_recording(
+    mut recorder: ResMut<ReplayRecorder>,
+    playback: Option<Res<ReplayPlayback>>,
+    seed: Res<GameSeed>,
+    maze_path: Res<MazePath>,
+    frightened_behaviour: Res<GhostFrightenedBehaviour>,
+) {
+    if playback.is_some() || recorder.0.ticks.is_empty() {
+        return;
+    }
+
+    // saved every time play stops so there is a replay even if the game is closed part way through
+    recorder.0.seed = seed.0;
+    recorder.0.maze = maze_path.0.clone();
+    recorder.0.frightened = frightened_behaviour.0;
+
+    if let Err(error) = fs::write(RECORDING_FILE, recorder.0.to_text()) {
+        error!("Could not save the replay to {}: {}", RECORDING_FILE, error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_line(source: &str) -> usize {
+        Replay::parse(source).err().expect("replay should not parse").line
+    }
+
+    #[test]
+    fn round_trip() {
+        let directions: Vec<Direction> = "789456123".chars().map(|character| char_to_input(character).unwrap()).collect();
+        // two full lines and a bit, with a checksum at the end of each
+        let ticks = (0..150).map(|tick| ReplayTick {
+            input: directions[tick % directions.len()],
+            checksum: (tick % TICKS_PER_LINE == TICKS_PER_LINE - 1 || tick == 149).then_some(0x0123_4567_89ab_cdef + tick as u64),
+        }).collect();
+
+        let replay = Replay {
+            seed: 1234,
+            maze: String::from("levels/testing_easy.maze"),
+            frightened: FrightenedBehaviour::Flee,
+            difficulty: Difficulty::from_name("hard").unwrap(),
+            movement: MovementOptions { input_buffer: true, cornering: true },
+            ghost_combo: vec![100, 300, 900],
+            ticks,
+        };
+
+        let text = replay.to_text();
+        let parsed = Replay::parse(&text).unwrap();
+
+        assert_eq!(parsed.seed, replay.seed);
+        assert_eq!(parsed.maze, replay.maze);
+        assert_eq!(parsed.frightened, replay.frightened);
+        assert_eq!(parsed.difficulty, replay.difficulty);
+        assert_eq!(parsed.movement, replay.movement);
+        assert_eq!(parsed.ghost_combo, replay.ghost_combo);
+        assert_eq!(parsed.ticks.len(), replay.ticks.len());
+        for (parsed_tick, tick) in parsed.ticks.iter().zip(&replay.ticks) {
+            assert_eq!(input_to_char(parsed_tick.input), input_to_char(tick.input));
+            assert_eq!(parsed_tick.checksum, tick.checksum);
+        }
+
+        assert_eq!(Replay::parse(&text).unwrap().to_text(), text);
+    }
+
+    #[test]
+    fn optional_header_keys_default() {
+        let replay = Replay::parse("seed: 7\nmaze: levels/classic.maze\n---\n5566 00000000000000ff\n").unwrap();
+
+        assert_eq!(replay.frightened, FrightenedBehaviour::Wander);
+        assert_eq!(replay.movement, MovementOptions::default());
+        assert_eq!(replay.ghost_combo, vec![200, 400, 800, 1600]);
+        assert_eq!(replay.ticks.len(), 4);
+        assert_eq!(replay.ticks[3].checksum, Some(0xff));
+    }
+
+    #[test]
+    fn bad_checksum() {
+        assert_eq!(error_line("seed: 7\nmaze: levels/classic.maze\n---\n5555 0000000000000001\n5555 notachecksum\n"), 5);
+    }
+
+    #[test]
+    fn unknown_tick_character() {
+        assert_eq!(error_line("seed: 7\nmaze: levels/classic.maze\n---\n5555\n55x5\n"), 5);
+        // 0 isn't a direction on the number pad either
+        assert_eq!(error_line("seed: 7\nmaze: levels/classic.maze\n---\n5505\n"), 4);
+    }
+
+    #[test]
+    fn missing_header_keys_reported_at_the_end_of_the_header() {
+        assert_eq!(error_line("maze: levels/classic.maze\n---\n5555\n"), 2);
+        assert_eq!(error_line("seed: 7\n; no maze\n---\n5555\n"), 3);
+        assert_eq!(error_line(""), 1);
+    }
+
+    #[test]
+    fn invalid_header_values() {
+        assert_eq!(error_line("seed: seven\nmaze: levels/classic.maze\n---\n"), 1);
+        assert_eq!(error_line("seed: 7\nmaze: levels/classic.maze\ncornering: maybe\n---\n"), 3);
+        assert_eq!(error_line("seed: 7\nspeed: fast\n---\n"), 2);
+        assert_eq!(error_line("seed: 7\nghost_combo: 200 lots\n---\n"), 2);
+    }
+}
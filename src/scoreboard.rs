@@ -2,13 +2,40 @@ use std::{fs::{self, File}, io::{prelude::*, BufReader}};
 
 use bevy::{input::{keyboard::{Key, KeyboardInput}, ButtonState}, prelude::*};
 
-use crate::{gamestates::{despawn_screen, GameState}, Score};
+use crate::{gamestates::{despawn_screen, GameState}, inputactions::{ActionInput, InputAction}, players::Players, Score};
+
+// the leaderboard is stored as "name:score" lines, highest score first
+const LEADERBOARD_FILE: &str = "leaderboard.txt";
+
+pub const LEADERBOARD_SIZE: usize = 10;
 
 #[derive(Component)]
 pub struct OnGameOverScreen;
 
+// the leaderboard entry for a player (starting from 0) to enter their initials in
 #[derive(Component)]
-pub struct PlayerLeaderboardEntry;
+pub struct PlayerLeaderboardEntry(pub usize);
+
+#[derive(Component)]
+struct InitialsPrompt;
+
+/*
+ * The players that made it onto the leaderboard, in the order they enter their initials, and whose turn it is
+ */
+#[derive(Resource, Default)]
+struct InitialsTurns {
+    players: Vec<usize>,
+    current: usize,
+}
+
+impl InitialsTurns {
+    fn player(&self) -> Option<usize> {
+        self.players.get(self.current).copied()
+    }
+}
+
+const ENTRY_TEXT_COLOUR: Color = Color::linear_rgb(0.0, 0.3, 0.0);
+const ENTRY_BACKGROUND_COLOUR: Color = Color::linear_rgb(1.0, 1.0, 1.0);
 
 #[derive(Component)]
 pub struct PassiveLeaderboardEntry;
@@ -17,6 +44,7 @@ pub struct ScoreBoardPlugin;
 
 impl Plugin for ScoreBoardPlugin {
     fn build(&self, app: &mut App) {
+        app.init_resource::<InitialsTurns>();
         app.add_systems(OnEnter(GameState::GameOver), setup_scoreboard);
         app.add_systems(Update, player_initials.run_if(in_state(GameState::GameOver)));
         app.add_systems(OnExit(GameState::GameOver), (save_scoreboard, despawn_screen::<OnGameOverScreen>).chain());
@@ -27,77 +55,99 @@ impl Plugin for ScoreBoardPlugin {
 struct LeaderboardItem {
     name: String,
     score_num: i32,
-    is_current_player: bool,
+    // the player (starting from 0) the entry is for if it was scored in this game
+    player: Option<usize>,
 }
 
 #[derive(Component, Clone)]
 struct Rank(u32);
 
+/*
+ * Read the saved leaderboard as (name, score) pairs, an empty leaderboard if there isn't one yet
+ */
+pub fn load_leaderboard() -> Vec<(String, i32)> {
+    let Ok(file) = File::open(LEADERBOARD_FILE) else {
+        return Vec::new();
+    };
+
+    let mut leaderboard = Vec::new();
+
+    for line_string in BufReader::new(file).lines().map_while(Result::ok) {
+        let split_strings: Vec<&str> = line_string.split(':').collect();
+        let mut split_strings = split_strings.iter();
+
+        if let Some(name) = split_strings.next() { // get the first item in the split
+            if let Some(score) = split_strings.next() { // get the second item in the split
+                if let Ok(score_number) = score.parse::<i32>() { // get the score as a number from the score string
+                    leaderboard.push((name.to_string(), score_number));
+                }
+            }
+        }
+    }
+
+    leaderboard
+}
+
+/*
+ * The initials prompt, which says which player it is for when there is more than one
+ */
+fn initials_prompt(player: usize, player_count: usize) -> String {
+    if player_count > 1 {
+        format!("Player {}, enter your initials:", player + 1)
+    } else {
+        "Enter your initials:".to_string()
+    }
+}
+
 fn setup_scoreboard(
     mut commands: Commands,
     score: Res<Score>,
+    players: Res<Players>,
 ) {
     // structure of leaderboard
     let mut leaderboard: Vec<LeaderboardItem> = Vec::new();
-    let mut leaderboard_has_space = true;
     let mut leaderboard_lowest = -1;
 
     // get the leaderboard from the file
-    if let Ok(file) = File::open("leaderboard.txt") {
-        // file opened successfully
-        let buf_reader = BufReader::new(file);
-
-        let mut leaderboard_count = 0;
-
-        for line in buf_reader.lines() {
-            if let Ok(line_string) = line {
-                let split_strings: Vec<&str> = line_string.split(':').collect();
-                let mut split_strings = split_strings.iter();
-
-
-                if let Some(name) = split_strings.next() { // get the first item in the split
-                    if let Some(score) = split_strings.next() { // get the second item in the split
-                        if let Ok(score_number) = score.parse::<i32>() { // get the score as a number from the score string
+    for (name, score_number) in load_leaderboard() {
+        leaderboard.push(LeaderboardItem { name, score_num: score_number, player: None });
 
-                            leaderboard.push(
-                                LeaderboardItem {
-                                    name: name.to_string(), score_num: score_number, is_current_player: false
-                                });
-
-                            if score_number < leaderboard_lowest || leaderboard_lowest == -1 {
-                                leaderboard_lowest = score_number;
-                            }
-                            leaderboard_count += 1;
-                        }
-                    }
-                }
-            }
-        }
-        if leaderboard_count >= 10 {
-            leaderboard_has_space = false;
+        if score_number < leaderboard_lowest || leaderboard_lowest == -1 {
+            leaderboard_lowest = score_number;
         }
     }
+    let leaderboard_has_space = leaderboard.len() < LEADERBOARD_SIZE;
 
 
-    // check if the new score makes it onto the leaderboard
-    let player_on_leaderboard = leaderboard_has_space || score.0 > leaderboard_lowest;
-
-    if player_on_leaderboard {
-        // insert the current player into the leaderboard
+    for (player, player_score) in players.scores(score.0).into_iter().enumerate() {
+        // check if the new score makes it onto the leaderboard
+        if !(leaderboard_has_space || player_score > leaderboard_lowest) {
+            continue;
+        }
 
         // find the index on the leaderboard for the player
         let mut insert_index = 0;
 
         for leaderboard_item in &leaderboard {
             // move down the leaderboard increasing our index each time we come across a value that is larger than the current score
-            if leaderboard_item.score_num > score.0 {
+            if leaderboard_item.score_num > player_score {
                 insert_index += 1;
             }
         }
 
-        leaderboard.insert(insert_index as usize, LeaderboardItem {name: "___".to_string(), score_num: score.0, is_current_player: true});
+        leaderboard.insert(insert_index as usize, LeaderboardItem {name: "___".to_string(), score_num: player_score, player: Some(player)});
     }
 
+    // both players getting on can push each other (or the other entries) off the bottom
+    leaderboard.truncate(LEADERBOARD_SIZE);
+
+    // the players on the leaderboard enter their initials one after the other, player one first
+    let mut entering_players: Vec<usize> = leaderboard.iter().filter_map(|leaderboard_item| leaderboard_item.player).collect();
+    entering_players.sort();
+    let player_on_leaderboard = !entering_players.is_empty();
+    let first_player = entering_players.first().copied().unwrap_or(0);
+    commands.insert_resource(InitialsTurns { players: entering_players, current: 0 });
+
     // display the leaderboard on the screen
 
     commands.spawn((
@@ -142,15 +192,18 @@ fn setup_scoreboard(
                 ..default()
             });
             if player_on_leaderboard {
-                gameover_message.spawn(TextBundle {
-                    text: Text::from_section(
-                        "Enter your initials:",
-                        TextStyle {
-                            font_size: 20.0,
-                            ..default()
-                        }),
-                    ..default()
-                });
+                gameover_message.spawn((
+                    TextBundle {
+                        text: Text::from_section(
+                            initials_prompt(first_player, players.count),
+                            TextStyle {
+                                font_size: 20.0,
+                                ..default()
+                            }),
+                        ..default()
+                    },
+                    InitialsPrompt,
+                ));
             }
         });
 
@@ -166,9 +219,8 @@ fn setup_scoreboard(
             ..default()
         }).with_children(| leaderboard_area | {
 
-            let mut lb_rank = 1;
             // now spawn the leaderboard items
-            for leaderboard_item in leaderboard {
+            for (lb_rank, leaderboard_item) in (1..).zip(leaderboard) {
 
                 let leaderboard_string = format!("{} {:>8}", &leaderboard_item.name, leaderboard_item.score_num); // max score will be 99,999,999
 
@@ -177,19 +229,21 @@ fn setup_scoreboard(
                      TextStyle {
                         font_size: 20.0,
                         color: 
-                            if leaderboard_item.is_current_player {Color::linear_rgb(0.0, 0.3, 0.0)}
+                            if leaderboard_item.player.is_some() {ENTRY_TEXT_COLOUR}
                             else {Color::linear_rgb(1.0, 1.0, 1.0)},
                         ..default()
                     });
 
-                if leaderboard_item.is_current_player {
+                if let Some(player) = leaderboard_item.player {
+                    // only the entry being typed in is highlighted, the next player's is left until it is their turn
                     leaderboard_area.spawn((
                         TextBundle {
                             text,
-                            background_color: BackgroundColor::from(Color::linear_rgb(1.0, 1.0, 1.0)),
+                            background_color: BackgroundColor::from(
+                                if player == first_player { ENTRY_BACKGROUND_COLOUR } else { Color::NONE }),
                             ..default()
                         },
-                        PlayerLeaderboardEntry,
+                        PlayerLeaderboardEntry(player),
                         leaderboard_item,
                         Rank(lb_rank),
                         ));
@@ -204,8 +258,6 @@ fn setup_scoreboard(
                         Rank(lb_rank),
                     ));
                 }
-
-                lb_rank += 1;
             }
         });
 
@@ -234,24 +286,51 @@ fn setup_scoreboard(
 
 }
 
+#[allow(clippy::too_many_arguments)]
 fn player_initials(
-    mut lb_player_entry: Query<&mut Text, With<PlayerLeaderboardEntry>>,
+    mut lb_player_entries: Query<(&mut Text, &mut BackgroundColor, &PlayerLeaderboardEntry), Without<InitialsPrompt>>,
+    mut prompt: Query<&mut Text, With<InitialsPrompt>>,
+    mut turns: ResMut<InitialsTurns>,
+    players: Res<Players>,
     mut event_reader_keys: EventReader<KeyboardInput>,
     mut game_state: ResMut<NextState<GameState>>,
+    input: ActionInput,
 ) {
+    if input.just_pressed(InputAction::Start) {
+        if turns.players.is_empty() {
+            // no initials to enter, so start again straight away
+            game_state.set(GameState::LevelSetup);
+        } else if turns.current + 1 < turns.players.len() {
+            // over to the next player to enter theirs
+            turns.current += 1;
+            let next_player = turns.player();
+
+            for (_, mut background, entry) in &mut lb_player_entries {
+                *background = BackgroundColor::from(
+                    if Some(entry.0) == next_player { ENTRY_BACKGROUND_COLOUR } else { Color::NONE });
+            }
+            if let (Ok(mut prompt), Some(next_player)) = (prompt.get_single_mut(), next_player) {
+                prompt.sections[0].value = initials_prompt(next_player, players.count);
+            }
+        } else {
+            // move on to continue - go back to the main menu
+            game_state.set(GameState::MainMenu);
+        }
+        return;
+    }
+
+    let entering_player = turns.player();
+
     for ev in event_reader_keys.read() {
         if ev.state == ButtonState::Released {
             continue;
         }
 
-        if let Ok(mut lb_player_entry) = lb_player_entry.get_single_mut() {
+        let lb_player_entry = lb_player_entries.iter_mut().find(|(_, _, entry)| Some(entry.0) == entering_player);
+        if let Some((mut lb_player_entry, _, _)) = lb_player_entry {
             if let Some(section) = lb_player_entry.sections.first_mut() {
 
                 match &ev.logical_key {
-                    Key::Space => {
-                        // move on to continue - go back to splashscreen
-                        game_state.set(GameState::SplashScreen);
-                    },
                     Key::Backspace => {
                         // get the position of the last user entered character
                         // go through each of the characters from the beginning to find this
@@ -277,23 +356,13 @@ fn player_initials(
                     _ => {}
                 }
             }
-        } else {
-            // no player entry to be gotten
-            // just check if the start button has been pressed
-            match &ev.logical_key {
-                Key::Space => {
-                    game_state.set(GameState::LevelSetup);
-                },
-                _ => {},
-            }
         }
-        
     }
 }
 
 fn save_scoreboard(
     leaderboard_items: Query<(&LeaderboardItem, &Rank)>,
-    player_lb_entry: Query<&Text, With<PlayerLeaderboardEntry>>,
+    player_lb_entries: Query<(&Text, &PlayerLeaderboardEntry)>,
     mut score: ResMut<Score>,
 ) {
     if leaderboard_items.is_empty() {
@@ -312,16 +381,16 @@ fn save_scoreboard(
     });
 
     // limit the leaderboard to 10
-    leaderboard = leaderboard.into_iter().take(10).collect();
+    leaderboard = leaderboard.into_iter().take(LEADERBOARD_SIZE).collect();
 
     let mut file_output = String::default();
     for (_, lb_item) in leaderboard {
         file_output.push_str(&format!("{}:{:?}\n",
-        if lb_item.is_current_player {
+        if let Some(player) = lb_item.player {
             let mut player_name_string = "   ".to_string();
-            if let Ok(player_name) = player_lb_entry.get_single() {
+            if let Some((player_name, _)) = player_lb_entries.iter().find(|(_, entry)| entry.0 == player) {
                 if let Some(player_name) = player_name.sections.first() {
-                    let player_name: String = player_name.value.chars().into_iter().take(3).collect();
+                    let player_name: String = player_name.value.chars().take(3).collect();
                     player_name_string = player_name.replace("_", " ");
                 }
             }
@@ -332,11 +401,8 @@ fn save_scoreboard(
         lb_item.score_num));
     }
 
-    match fs::write("leaderboard.txt", file_output) {
-        Err(_) => {
-            info!("Cannot write to leaderboard!");
-        },
-        _ => {},
+    if fs::write(LEADERBOARD_FILE, file_output).is_err() {
+        info!("Cannot write to leaderboard!");
     }
 
     // reset score to 0
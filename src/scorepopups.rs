@@ -0,0 +1,79 @@
+use bevy::prelude::*;
+
+use crate::gamelogic::OnGameplayScreen;
+
+/*
+ * Score popups
+ *
+ * The points for eating a ghost, a fruit or a ghost weakness token float up from where it was eaten for a moment
+ */
+
+const POPUP_TIME: f32 = 1.0;
+
+// how far the popup floats up over its lifetime, in px
+const POPUP_RISE: f32 = 12.0;
+
+const POPUP_COLOUR: Color = Color::srgb(0.0, 1.0, 1.0);
+
+#[derive(Component)]
+pub struct ScorePopup {
+    timer: Timer,
+    start: Vec2,
+}
+
+pub struct ScorePopupPlugin;
+
+impl Plugin for ScorePopupPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, update_score_popups);
+    }
+}
+
+/*
+ * Show the points at the given screen position
+ */
+pub fn spawn_score_popup(commands: &mut Commands, screen_pos: Vec2, points: i32) {
+    commands.spawn((
+        Text2dBundle {
+            text: Text::from_section(
+                points.to_string(),
+                TextStyle {
+                    font_size: 12.0,
+                    color: POPUP_COLOUR,
+                    ..default()
+                },
+            ),
+            // above everything else on the board
+            transform: Transform::from_xyz(screen_pos.x, screen_pos.y, 0.1),
+            ..default()
+        },
+        ScorePopup {
+            timer: Timer::from_seconds(POPUP_TIME, TimerMode::Once),
+            start: screen_pos,
+        },
+        OnGameplayScreen,
+    ));
+}
+
+fn update_score_popups(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut popups: Query<(Entity, &mut ScorePopup, &mut Transform, &mut Text)>,
+) {
+    for (entity, mut popup, mut transform, mut text) in &mut popups {
+        popup.timer.tick(time.delta());
+        if popup.timer.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        let progress = popup.timer.fraction();
+        transform.translation.y = popup.start.y + (POPUP_RISE * progress);
+
+        // fade out over the second half
+        let alpha = (2.0 * (1.0 - progress)).min(1.0);
+        for section in text.sections.iter_mut() {
+            section.style.color.set_alpha(alpha);
+        }
+    }
+}
@@ -0,0 +1,224 @@
+use std::{env, fs, path::PathBuf};
+
+use bevy::{prelude::*, window::PrimaryWindow};
+
+use crate::inputactions::InputBindings;
+use crate::levels::Difficulty;
+use crate::simulation::MovementOptions;
+use crate::{MAX_LIVES, WINDOW_SIZE};
+
+/*
+ * Player settings
+ *
+ * The things that can be changed from the options screen, saved to settings.txt in the user's config directory
+ * (~/.config/rust_pacman on Linux, ~/Library/Application Support/rust_pacman on macOS and %APPDATA%\rust_pacman on
+ * Windows) as "key: value" lines. Anything missing or invalid in the file is left at its default.
+ *
+ *  window_scale: 1.5
+ *  lives: 3
+ *  difficulty: normal
+ *  input_buffer: on
+ *  cornering: off
+ *  co_op_scores: shared
+ *  keys_up: ArrowUp KeyW
+ *  buttons_up: DPadUp
+ *
+ * There is a keys_ and buttons_ line for each input action (see inputactions.rs). Changes apply straight away - the
+ * window is resized and the new bindings used as soon as they change, the lives and difficulty are
+ * used from the next game started along with the movement options and co-op scores.
+ */
+
+const SETTINGS_DIRECTORY: &str = "rust_pacman";
+const SETTINGS_FILE: &str = "settings.txt";
+
+pub const WINDOW_SCALES: [f32; 5] = [1.0, 1.5, 2.0, 2.5, 3.0];
+
+#[derive(Resource, Clone, PartialEq, Debug)]
+pub struct Settings {
+    pub window_scale: f32,
+    pub starting_lives: i32,
+    pub difficulty: Difficulty,
+    pub movement: MovementOptions,
+    // in co-op, whether each player has their own score rather than sharing one
+    pub split_scores: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            window_scale: 1.0,
+            starting_lives: 3,
+            difficulty: Difficulty::default(),
+            movement: MovementOptions { input_buffer: true, cornering: false },
+            split_scores: false,
+        }
+    }
+}
+
+/*
+ * The directory the settings are saved in, None if there is no home directory to put it in
+ */
+fn config_directory() -> Option<PathBuf> {
+    let base = if cfg!(target_os = "windows") {
+        PathBuf::from(env::var_os("APPDATA")?)
+    } else if cfg!(target_os = "macos") {
+        PathBuf::from(env::var_os("HOME")?).join("Library").join("Application Support")
+    } else {
+        match env::var_os("XDG_CONFIG_HOME").filter(|path| !path.is_empty()) {
+            Some(path) => PathBuf::from(path),
+            None => PathBuf::from(env::var_os("HOME")?).join(".config"),
+        }
+    };
+
+    Some(base.join(SETTINGS_DIRECTORY))
+}
+
+pub fn on_off(on: bool) -> &'static str {
+    if on { "on" } else { "off" }
+}
+
+pub fn parse_on_off(value: &str) -> Option<bool> {
+    match value {
+        "on" => Some(true),
+        "off" => Some(false),
+        _ => None,
+    }
+}
+
+pub fn co_op_scores_name(split_scores: bool) -> &'static str {
+    if split_scores { "split" } else { "shared" }
+}
+
+fn settings_path() -> Option<PathBuf> {
+    Some(config_directory()?.join(SETTINGS_FILE))
+}
+
+impl Settings {
+    /*
+     * Read the settings file, along with the input bindings kept in it, the defaults are used for anything that isn't
+     * in it
+     */
+    pub fn load() -> (Settings, InputBindings) {
+        let mut settings = Settings::default();
+        let mut bindings = InputBindings::default();
+
+        let Some(path) = settings_path() else {
+            return (settings, bindings);
+        };
+        let Ok(source) = fs::read_to_string(&path) else {
+            return (settings, bindings);
+        };
+
+        for line in source.lines() {
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+
+            let key = key.trim();
+            let valid = match key {
+                "window_scale" => match value.parse() {
+                    Ok(scale) if WINDOW_SCALES.contains(&scale) => { settings.window_scale = scale; true },
+                    _ => false,
+                },
+                "lives" => match value.parse() {
+                    Ok(lives) if (1..=MAX_LIVES).contains(&lives) => { settings.starting_lives = lives; true },
+                    _ => false,
+                },
+                "difficulty" => match Difficulty::from_name(value) {
+                    Some(difficulty) => { settings.difficulty = difficulty; true },
+                    None => false,
+                },
+                "input_buffer" => match parse_on_off(value) {
+                    Some(on) => { settings.movement.input_buffer = on; true },
+                    None => false,
+                },
+                "cornering" => match parse_on_off(value) {
+                    Some(on) => { settings.movement.cornering = on; true },
+                    None => false,
+                },
+                "co_op_scores" => match value {
+                    "shared" | "split" => { settings.split_scores = value == "split"; true },
+                    _ => false,
+                },
+                // settings from newer versions of the game are left alone
+                _ => bindings.parse_setting(key, value).unwrap_or(true),
+            };
+
+            if !valid {
+                warn!("Ignoring invalid {} in {}: {}", key, path.display(), value);
+            }
+        }
+
+        (settings, bindings)
+    }
+
+    pub fn to_text(&self, bindings: &InputBindings) -> String {
+        format!(
+            "window_scale: {}\nlives: {}\ndifficulty: {}\ninput_buffer: {}\ncornering: {}\nco_op_scores: {}\n{}",
+            self.window_scale, self.starting_lives, self.difficulty.name(),
+            on_off(self.movement.input_buffer), on_off(self.movement.cornering), co_op_scores_name(self.split_scores),
+            bindings.to_text())
+    }
+
+    pub fn save(&self, bindings: &InputBindings) {
+        let Some(directory) = config_directory() else {
+            warn!("No config directory to save the settings in");
+            return;
+        };
+        let path = directory.join(SETTINGS_FILE);
+
+        if let Err(error) = fs::create_dir_all(&directory).and_then(|_| fs::write(&path, self.to_text(bindings))) {
+            error!("Could not save the settings to {}: {}", path.display(), error);
+        }
+    }
+
+    /*
+     * The next window scale up or down from the current one, wrapping around at the ends
+     */
+    pub fn cycle_window_scale(&mut self, forwards: bool) {
+        let index = WINDOW_SCALES.iter().position(|scale| *scale == self.window_scale).unwrap_or(0);
+        let count = WINDOW_SCALES.len();
+        self.window_scale = WINDOW_SCALES[if forwards { (index + 1) % count } else { (index + count - 1) % count }];
+    }
+
+    pub fn change_starting_lives(&mut self, up: bool) {
+        self.starting_lives = (self.starting_lives + if up { 1 } else { -1 }).clamp(1, MAX_LIVES);
+    }
+
+    pub fn cycle_difficulty(&mut self, forwards: bool) {
+        let index = Difficulty::ALL.iter().position(|difficulty| *difficulty == self.difficulty).unwrap_or(0);
+        let count = Difficulty::ALL.len();
+        self.difficulty = Difficulty::ALL[if forwards { (index + 1) % count } else { (index + count - 1) % count }];
+    }
+}
+
+pub struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        let (settings, bindings) = Settings::load();
+
+        app.insert_resource(settings)
+            .insert_resource(bindings)
+            .add_systems(Update, apply_window_scale.run_if(resource_changed::<Settings>));
+    }
+}
+
+fn apply_window_scale(
+    settings: Res<Settings>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    let Ok(mut window) = windows.get_single_mut() else {
+        return;
+    };
+    // on top of whatever scaling the screen already has
+    let scale_factor = window.resolution.base_scale_factor() * settings.window_scale;
+    if window.resolution.scale_factor_override() == Some(scale_factor) {
+        return;
+    }
+
+    // the game is still laid out at the same size, everything is just drawn bigger
+    window.resolution.set_scale_factor_override(Some(scale_factor));
+    window.resolution.set(WINDOW_SIZE.x, WINDOW_SIZE.y);
+}
@@ -0,0 +1,1096 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+
+use crate::gamelogic::{at_decision_point, check_collision, get_new_position_alt, BlockReward, Direction, GameLogic, Horizontal, Vertical};
+use crate::fruit::{BonusFruit, Fruit, FRUIT_TOKENS_EATEN};
+use crate::gamerng::GameRng;
+use crate::ghost::{new_ghosts, roaming_status, FrightenedBehaviour, Ghost, GhostActionsStatus, GhostContext, GhostPositionStatus};
+use crate::ghostwaves::GhostWaves;
+use crate::levels::{Difficulty, LevelSettings};
+use crate::pathfinding::Pathfinder;
+
+/*
+ * The game simulation
+ *
+ * Holds everything that happens during play (the player, the ghosts, the tokens left on the board and the scoring)
+ * in gameboard coordinates and moves it all on a tick at a time. There is usually one player, a second can be added
+ * to play together on the same board - the ghosts go after whichever player is nearest to them, and a player that is
+ * caught leaves the board while the other carries on. Nothing in here knows about sprites or the screen,
+ * so whole games can be played without a window - the Bevy side feeds in the player input and draws whatever state
+ * the simulation is in.
+ */
+
+// sizes used for collisions, in tiles
+const CHARACTER_SIZE: f32 = 21.0 / 15.0;
+const POINT_TOKEN_SIZE: f32 = 6.0 / 15.0;
+const GHOST_WEAKNESS_TOKEN_SIZE: f32 = 16.0 / 15.0;
+const FRUIT_SIZE: f32 = 1.0;
+
+// FNV-1a, used for the checksum as it has to come out the same in every build of the game
+const FNV_OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+const FNV_PRIME: u64 = 0x100000001b3;
+
+// how long everything stops for when a ghost is eaten, in seconds
+pub const GHOST_EATEN_FREEZE_TIME: f32 = 1.0;
+
+// with cornering, how far from the middle of a junction (in tiles) the player can start turning
+const CORNERING_THRESHOLD: f32 = 0.4;
+
+/*
+ * Optional changes to how the player moves, with both off the player only turns when the direction is held down as
+ * they reach the middle of a junction
+ */
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct MovementOptions {
+    // a direction pressed is remembered until the player is able to go that way
+    pub input_buffer: bool,
+    // turns can be started a little before (or after) the middle of a junction, moving diagonally to cut the corner
+    pub cornering: bool,
+}
+
+/*
+ * Points awarded for everything that can be eaten (apart from the fruit, which has its own points)
+ */
+#[derive(Clone, Debug)]
+pub struct Scoring {
+    pub point_token: i32,
+    pub ghost_weakness_token: i32,
+    // points for each ghost eaten in a row on one ghost weakness token, the last entry is used for any after that
+    pub ghost_combo: Vec<i32>,
+}
+
+impl Default for Scoring {
+    fn default() -> Self {
+        Scoring {
+            point_token: 10,
+            ghost_weakness_token: 50,
+            ghost_combo: vec![200, 400, 800, 1600],
+        }
+    }
+}
+
+impl Scoring {
+    /*
+     * A ghost combo table written as points separated by spaces ("200 400 800 1600"), None if it isn't one
+     */
+    pub fn parse_ghost_combo(value: &str) -> Option<Vec<i32>> {
+        let combo: Vec<i32> = value.split_whitespace()
+            .map(|points| points.parse().ok().filter(|points| *points >= 0))
+            .collect::<Option<_>>()?;
+
+        (!combo.is_empty()).then_some(combo)
+    }
+
+    pub fn ghost_combo_text(combo: &[i32]) -> String {
+        combo.iter().map(|points| points.to_string()).collect::<Vec<_>>().join(" ")
+    }
+
+    /*
+     * Points for eating a ghost when it is the given number in a row (starting from 1)
+     */
+    pub fn ghost_points(&self, consecutive_kills: usize) -> i32 {
+        let index = consecutive_kills.saturating_sub(1).min(self.ghost_combo.len().saturating_sub(1));
+        self.ghost_combo.get(index).copied().unwrap_or(0)
+    }
+}
+
+pub struct PlayerState {
+    pub position: Vec2,
+    pub speed: f32,
+    pub direction_of_travel: Direction,
+    // the last direction pressed, waiting for a chance to turn that way when the input is buffered
+    pub desired_direction: Direction,
+    // points scored by this player since the start of the game
+    pub score: i32,
+    // false while the player is off the board after being caught, with another player still playing
+    pub in_play: bool,
+
+    // the score when the level started, to go back to if it is restarted
+    level_start_score: i32,
+    // counts down to the player coming back on to the board after being caught, if they are coming back
+    respawn: Option<Timer>,
+}
+
+impl PlayerState {
+    fn new(position: Vec2) -> Self {
+        PlayerState {
+            position,
+            speed: 0.0,
+            direction_of_travel: Direction::ZERO,
+            desired_direction: Direction::ZERO,
+            score: 0,
+            in_play: true,
+            level_start_score: 0,
+            respawn: None,
+        }
+    }
+
+    /*
+     * Back to the start position, stood still and ready to play
+     */
+    fn put_back(&mut self, position: Vec2, speed: f32) {
+        self.position = position;
+        self.speed = speed;
+        self.direction_of_travel = Direction::ZERO;
+        self.desired_direction = Direction::ZERO;
+        self.in_play = true;
+        self.respawn = None;
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SimulationStatus {
+    Playing,
+    PlayerCaught,
+    LevelCleared,
+}
+
+/*
+ * Things that happened during a tick that the rest of the game might want to react to
+ */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum SimulationEvent {
+    TokenEaten { player: usize, cell: IVec2, reward: BlockReward, points: i32 },
+    GhostEaten { player: usize, ghost: usize, position: Vec2, points: i32 },
+    FruitEaten { player: usize, fruit: Fruit, position: Vec2, points: i32 },
+    // the status is only PlayerCaught once every player has been caught, until then the others carry on
+    PlayerCaught { player: usize },
+    LevelCleared,
+}
+
+pub struct Simulation {
+    // the gameboard as it is now - eaten tokens are removed from it
+    pub board: GameLogic,
+    pub players: Vec<PlayerState>,
+    pub ghosts: Vec<Ghost>,
+    pub status: SimulationStatus,
+    // points scored and ticks played since the start of the game
+    pub score: i32,
+    pub ticks: usize,
+    // the level being played, starting from 1, and the speeds and timings for it
+    pub level: u32,
+    pub settings: LevelSettings,
+    pub difficulty: Difficulty,
+    // the bonus fruit on the board, if there is one, and every fruit eaten this game
+    pub fruit: Option<BonusFruit>,
+    pub collected_fruit: Vec<Fruit>,
+    pub scoring: Scoring,
+    pub frightened_behaviour: FrightenedBehaviour,
+    pub movement: MovementOptions,
+    // a level has been restarted part way through, so the game can't be played out again from the start
+    pub restarted: bool,
+    // the ghost steered by a second player in a versus match
+    pub human_ghost: Option<usize>,
+
+    // the gameboard the level started with, so the player and ghosts can be put back after losing a life
+    level_board: GameLogic,
+    // the score and fruit eaten when the level started, to go back to if it is restarted
+    level_start_score: i32,
+    level_start_fruit: usize,
+    tokens_left: usize,
+    tokens_eaten: usize,
+    consecutive_kills: usize,
+    // everything stops for a moment after a ghost has been eaten
+    freeze: Option<Timer>,
+
+    ghost_waves: GhostWaves,
+    pathfinder: Pathfinder,
+    rng: GameRng,
+}
+
+impl Simulation {
+    pub fn new(board: GameLogic, seed: u64) -> Self {
+        let mut simulation = Simulation {
+            board: board.clone(),
+            players: vec![PlayerState::new(board.player_start)],
+            ghosts: Vec::new(),
+            status: SimulationStatus::Playing,
+            score: 0,
+            ticks: 0,
+            level: 1,
+            settings: LevelSettings::for_level(1),
+            difficulty: Difficulty::default(),
+            fruit: None,
+            collected_fruit: Vec::new(),
+            scoring: Scoring::default(),
+            frightened_behaviour: FrightenedBehaviour::default(),
+            movement: MovementOptions::default(),
+            restarted: false,
+            human_ghost: None,
+            level_board: board.clone(),
+            level_start_score: 0,
+            level_start_fruit: 0,
+            tokens_left: 0,
+            tokens_eaten: 0,
+            consecutive_kills: 0,
+            freeze: None,
+            ghost_waves: GhostWaves::default(),
+            pathfinder: Pathfinder::default(),
+            rng: GameRng::new(seed),
+        };
+        simulation.start_level(board, 1);
+        simulation
+    }
+
+    /*
+     * Add another player to play at the same time, starting from the same place as the first
+     */
+    pub fn add_player(&mut self) {
+        let mut player = PlayerState::new(self.level_board.player_start);
+        player.speed = self.settings.player_speed;
+        self.players.push(player);
+    }
+
+    /*
+     * Start the given level on the gameboard, with all of its tokens back
+     */
+    pub fn start_level(&mut self, board: GameLogic, level: u32) {
+        self.level = level;
+        self.settings = self.difficulty.apply(LevelSettings::for_level(level));
+        self.level_start_score = self.score;
+        self.level_start_fruit = self.collected_fruit.len();
+        for player in self.players.iter_mut() {
+            player.level_start_score = player.score;
+        }
+
+        // any routes worked out for the last gameboard are no use now
+        self.pathfinder.clear();
+
+        self.tokens_left = board.game_blocks.iter()
+            .filter(|block| !matches!(block.block_reward, BlockReward::Nothing))
+            .count();
+        self.tokens_eaten = 0;
+        self.level_board = board.clone();
+        self.board = board;
+
+        self.reset_positions();
+    }
+
+    /*
+     * Change the difficulty, the current level starts again with it so this is meant for before play has started
+     */
+    pub fn set_difficulty(&mut self, difficulty: Difficulty) {
+        self.difficulty = difficulty;
+        self.start_level(self.level_board.clone(), self.level);
+    }
+
+    /*
+     * Start the current level again from the beginning, as if it had only just been reached
+     */
+    pub fn restart_level(&mut self) {
+        self.score = self.level_start_score;
+        self.collected_fruit.truncate(self.level_start_fruit);
+        for player in self.players.iter_mut() {
+            player.score = player.level_start_score;
+        }
+        self.restarted = true;
+
+        self.start_level(self.level_board.clone(), self.level);
+    }
+
+    /*
+     * Put the players and ghosts back to the start after a life has been lost, the tokens stay as they are
+     */
+    pub fn reset_positions(&mut self) {
+        for player in self.players.iter_mut() {
+            player.put_back(self.level_board.player_start, self.settings.player_speed);
+        }
+        self.ghosts = new_ghosts(&self.level_board, &self.settings);
+        if let Some(ghost) = self.human_ghost.and_then(|index| self.ghosts.get_mut(index)) {
+            ghost.human_controlled = true;
+        }
+        self.consecutive_kills = 0;
+        self.freeze = None;
+        self.fruit = None;
+
+        self.ghost_waves = GhostWaves::for_level(self.level);
+
+        self.status = SimulationStatus::Playing;
+    }
+
+    /*
+     * Hand the ghost with the given name over to a second player to steer, for a versus match
+     */
+    pub fn control_ghost(&mut self, name: &str) {
+        self.human_ghost = self.ghosts.iter().position(|ghost| ghost.name == name);
+        if let Some(ghost) = self.human_ghost.and_then(|index| self.ghosts.get_mut(index)) {
+            ghost.human_controlled = true;
+        }
+    }
+
+    /*
+     * The direction the second player is pressing for the ghost they are steering, it goes that way at the next
+     * junction it can
+     */
+    pub fn steer_ghost(&mut self, mut direction: Direction) {
+        if direction.vertical == Vertical::Zero && direction.horizontal == Horizontal::Zero {
+            return;
+        }
+        let Some(ghost) = self.human_ghost.and_then(|index| self.ghosts.get_mut(index)) else {
+            return;
+        };
+
+        // ghosts only go one way at a time, so holding two directions down means turning off the way it is going
+        if direction.vertical != Vertical::Zero && direction.horizontal != Horizontal::Zero {
+            if ghost.direction_of_travel.horizontal == Horizontal::Zero {
+                direction.vertical = Vertical::Zero;
+            } else {
+                direction.horizontal = Horizontal::Zero;
+            }
+        }
+        ghost.human_direction = direction;
+    }
+
+    /*
+     * Bring a player that has been caught back on to the board at the start position after the given time
+     */
+    pub fn respawn_player(&mut self, player: usize, delay: Duration) {
+        if let Some(player) = self.players.get_mut(player) {
+            player.respawn = Some(Timer::new(delay, TimerMode::Once));
+        }
+    }
+
+    /*
+     * Move everything on by one tick with the given direction held down by the player
+     */
+    pub fn tick(&mut self, input: Direction, delta: Duration) -> Vec<SimulationEvent> {
+        self.tick_players(&[input], delta)
+    }
+
+    /*
+     * Move everything on by one tick with the direction held down by each player, in player order
+     */
+    pub fn tick_players(&mut self, inputs: &[Direction], delta: Duration) -> Vec<SimulationEvent> {
+        let mut events = Vec::new();
+
+        if self.status != SimulationStatus::Playing {
+            return events;
+        }
+        self.ticks += 1;
+
+        if let Some(freeze) = &mut self.freeze {
+            if !freeze.tick(delta).finished() {
+                return events;
+            }
+            self.freeze = None;
+        }
+
+        self.update_respawns(delta);
+        for player in 0..self.players.len() {
+            self.move_player(player, inputs.get(player).copied().unwrap_or(Direction::ZERO), delta);
+        }
+        self.eat_tokens(&mut events);
+        self.update_fruit(delta, &mut events);
+
+        if self.status == SimulationStatus::Playing {
+            self.update_ghost_waves(delta);
+            self.move_ghosts(delta);
+            self.check_ghost_player_collision(&mut events);
+            self.update_weakened_timers(delta);
+        }
+
+        for event in &events {
+            if let SimulationEvent::TokenEaten { player, points, .. } | SimulationEvent::GhostEaten { player, points, .. } |
+                SimulationEvent::FruitEaten { player, points, .. } = event {
+                self.score += points;
+                self.players[*player].score += points;
+            }
+        }
+
+        events
+    }
+
+    /*
+     * True while everything is stopped after a ghost has been eaten
+     */
+    pub fn frozen(&self) -> bool {
+        self.freeze.is_some()
+    }
+
+    /*
+     * True while a weakened ghost is showing white as it flashes to warn that it is about to recover
+     */
+    pub fn ghost_flashing(&self, ghost: &Ghost) -> bool {
+        let (Some(timer), GhostActionsStatus::Weakened) = (&ghost.time_weakened, ghost.actions_status) else {
+            return false;
+        };
+
+        let flash_time = self.settings.frightened_flash_time.min(timer.duration().as_secs_f32());
+        let remaining = timer.remaining_secs();
+        if self.settings.frightened_flashes == 0 || flash_time <= 0.0 || remaining > flash_time {
+            return false;
+        }
+
+        // each flash is white for the first half and back to the weakened colour for the second
+        let flash_length = flash_time / self.settings.frightened_flashes as f32;
+        ((flash_time - remaining) / flash_length).fract() < 0.5
+    }
+
+    pub fn tokens_left(&self) -> usize {
+        self.tokens_left
+    }
+
+    /*
+     * A hash of the score and where everything is - simulations that have been given the same seed and inputs
+     * always have the same checksum
+     */
+    pub fn checksum(&self) -> u64 {
+        let values = [self.score as u32].into_iter()
+            .chain(self.players.iter().flat_map(|player| [player.position.x.to_bits(), player.position.y.to_bits()]))
+            .chain(self.ghosts.iter().flat_map(|ghost| [ghost.position.x.to_bits(), ghost.position.y.to_bits()]));
+
+        values.fold(FNV_OFFSET_BASIS, |hash, value| {
+            value.to_le_bytes().iter().fold(hash, |hash, byte| (hash ^ *byte as u64).wrapping_mul(FNV_PRIME))
+        })
+    }
+
+    fn update_respawns(&mut self, delta: Duration) {
+        let (start, speed) = (self.level_board.player_start, self.settings.player_speed);
+
+        for player in self.players.iter_mut() {
+            let Some(timer) = &mut player.respawn else {
+                continue;
+            };
+            if timer.tick(delta).finished() {
+                player.put_back(start, speed);
+            }
+        }
+    }
+
+    fn move_player(&mut self, player: usize, input: Direction, delta: Duration) {
+        let movement = self.movement;
+        let game_logic = &self.board;
+        let player = &mut self.players[player];
+        if !player.in_play {
+            return;
+        }
+
+        let movement_amount = player.speed * delta.as_secs_f32();
+
+        // a quick tap just before a junction is remembered until the turn can be made
+        let pressed_direction = if movement.input_buffer {
+            if input.vertical != Vertical::Zero || input.horizontal != Horizontal::Zero {
+                player.desired_direction = input;
+            }
+            player.desired_direction
+        } else {
+            input
+        };
+
+        let current_pos = player.position;
+
+        let mut potential_pos: (Vec2, bool) = (Vec2{x:0.0, y:0.0}, true);
+
+        // compare pressed direction to current direction of travel (favor vertical direction changes)
+        if pressed_direction.vertical != Vertical::Zero && pressed_direction.vertical != player.direction_of_travel.vertical {
+            // we are only looking at the vertical direction here
+            // we have pressed the opposite direction to which we were moving before, try to move back the way we have come
+            //  or we are changing direction down a hallway
+            //  or we have been sat not moving at all
+
+            let vertical_direction = Direction{vertical: pressed_direction.vertical, horizontal: Horizontal::Zero};
+
+            let mut skip_get_pos = false;
+
+            // if the player was not already going vertical (they are turning from a horizontal direction of travel to turn a 90 degree corner)
+            if player.direction_of_travel.vertical == Vertical::Zero {
+                // check if they are close enough to the center coordinate of a cell (only allow turning down a corridor if we are close enough to it)
+                // don't let the turn happen if we are too far away from the center position -- ASSUMPTION: ALL CORRIDOORS ARE ONLY 1 BLOCK WIDE
+
+                skip_get_pos = !can_turn(current_pos, player.direction_of_travel, movement.cornering);
+                potential_pos.1 = skip_get_pos;
+            }
+
+            // if no collision detected yet, get the new position
+            if !skip_get_pos {
+                potential_pos = get_new_position_alt(game_logic, current_pos, vertical_direction, movement_amount);
+            }
+
+            // check if there was any collision detected at all in this direction
+            if !potential_pos.1 {
+
+                // set new player direction of travel
+                player.direction_of_travel = vertical_direction;
+
+                // snap horizontal position to the nearest whole number (when cornering it moves across a bit at a time instead)
+                if !movement.cornering {
+                    potential_pos.0.x = potential_pos.0.x.round();
+                }
+            }
+        }
+
+        // now check the horizontal direction if the vertical was not fruitful
+        if potential_pos.1 && pressed_direction.horizontal != Horizontal::Zero && pressed_direction.horizontal != player.direction_of_travel.horizontal {
+
+            let horizontal_direction = Direction{vertical: Vertical::Zero, horizontal: pressed_direction.horizontal};
+
+            let mut skip_get_pos = false;
+
+            // if the player was not already going horizontal (they are turning from a vertical direction of travel to turn a 90 degree corner)
+            if player.direction_of_travel.horizontal == Horizontal::Zero {
+                // check if they are close enough to the center coordinate of a cell (only allow turning down a corridor if we are close enough to it)
+                // don't let the turn happen if we are too far away from the center position -- ASSUMPTION: ALL CORRIDOORS ARE ONLY 1 BLOCK WIDE
+
+                skip_get_pos = !can_turn(current_pos, player.direction_of_travel, movement.cornering);
+                potential_pos.1 = skip_get_pos;
+            }
+
+            // if no collision detected yet, get the new position
+            if !skip_get_pos {
+                potential_pos = get_new_position_alt(game_logic, current_pos, horizontal_direction, movement_amount);
+            }
+
+            // check if there was any collision detected at all in this direction
+            if !potential_pos.1 {
+                // set new player direction of travel
+                player.direction_of_travel = horizontal_direction;
+
+                // snap vertical position to the nearest whole number (when cornering it moves down a bit at a time instead)
+                if !movement.cornering {
+                    potential_pos.0.y = potential_pos.0.y.round();
+                }
+            }
+        }
+
+        if potential_pos.1 {
+            // neither of the player inputs directions are valid or are maybe are not present
+            // for now then we will try to continue in the same direction as we were moving before
+
+            potential_pos = get_new_position_alt(game_logic, current_pos,
+                player.direction_of_travel, movement_amount);
+        }
+
+        if potential_pos.1 {
+            // we cannot move anymore - stop moving now!
+            player.direction_of_travel = Direction::ZERO;
+
+            // snap to the block position so that we are directly on the path
+            player.position = current_pos.round();
+        } else {
+            // we have found a valid new position, move to this position
+            player.position = potential_pos.0;
+
+            // part way round a corner - carry on moving into the middle of the path as well as along it
+            if movement.cornering {
+                if player.direction_of_travel.vertical != Vertical::Zero {
+                    player.position.x = move_towards(player.position.x, player.position.x.round(), movement_amount);
+                } else if player.direction_of_travel.horizontal != Horizontal::Zero {
+                    player.position.y = move_towards(player.position.y, player.position.y.round(), movement_amount);
+                }
+            }
+        }
+
+        // the buffered direction has been used once the player is going that way
+        if player.desired_direction.vertical == player.direction_of_travel.vertical {
+            player.desired_direction.vertical = Vertical::Zero;
+        }
+        if player.desired_direction.horizontal == player.direction_of_travel.horizontal {
+            player.desired_direction.horizontal = Horizontal::Zero;
+        }
+    }
+
+    /*
+     * Eat any tokens the players are touching
+     */
+    fn eat_tokens(&mut self, events: &mut Vec<SimulationEvent>) {
+        for player in 0..self.players.len() {
+            if self.players[player].in_play {
+                self.eat_player_tokens(player, events);
+            }
+        }
+
+        if self.tokens_left == 0 {
+            self.status = SimulationStatus::LevelCleared;
+            events.push(SimulationEvent::LevelCleared);
+        }
+    }
+
+    fn eat_player_tokens(&mut self, player: usize, events: &mut Vec<SimulationEvent>) {
+        let position = self.players[player].position;
+        let player_rect = Rect::from_center_size(position, Vec2::splat(CHARACTER_SIZE));
+
+        // tokens are smaller than a tile so only the cells right next to the player can be touching it
+        let player_cell = position.round().as_ivec2();
+
+        for row_offset in -1..=1 {
+            for col_offset in -1..=1 {
+                let cell = player_cell + IVec2::new(col_offset, row_offset);
+                let Some(block) = self.board.get_block_mut(cell.x, cell.y) else {
+                    continue;
+                };
+
+                let (token_size, points) = match block.block_reward {
+                    BlockReward::PointToken => (POINT_TOKEN_SIZE, self.scoring.point_token),
+                    BlockReward::GhostWeaknessToken => (GHOST_WEAKNESS_TOKEN_SIZE, self.scoring.ghost_weakness_token),
+                    BlockReward::Nothing => continue,
+                };
+
+                if !check_collision(Rect::from_center_size(cell.as_vec2(), Vec2::splat(token_size)), player_rect) {
+                    continue;
+                }
+
+                // collision occured - remove the token and add the associated points to the score
+                let reward = block.block_reward;
+                block.block_reward = BlockReward::Nothing;
+                self.tokens_left -= 1;
+                self.tokens_eaten += 1;
+                events.push(SimulationEvent::TokenEaten { player, cell, reward, points });
+
+                if FRUIT_TOKENS_EATEN.contains(&self.tokens_eaten) {
+                    self.fruit = Some(BonusFruit::new(self.settings.fruit, self.level_board.fruit_position));
+                }
+
+                if let BlockReward::GhostWeaknessToken = reward {
+                    self.weaken_ghosts();
+                }
+            }
+        }
+    }
+
+    /*
+     * Eat the bonus fruit if the player is touching it, or take it away once it has been out for long enough
+     */
+    fn update_fruit(&mut self, delta: Duration, events: &mut Vec<SimulationEvent>) {
+        if self.status != SimulationStatus::Playing {
+            return;
+        }
+
+        let Some(fruit) = &mut self.fruit else {
+            return;
+        };
+
+        let fruit_rect = Rect::from_center_size(fruit.position, Vec2::splat(FRUIT_SIZE));
+        let eaten_by = self.players.iter().position(|player| {
+            player.in_play && check_collision(fruit_rect, Rect::from_center_size(player.position, Vec2::splat(CHARACTER_SIZE)))
+        });
+
+        if let Some(player) = eaten_by {
+            let (position, fruit) = (fruit.position, fruit.fruit);
+            self.collected_fruit.push(fruit);
+            self.fruit = None;
+            events.push(SimulationEvent::FruitEaten { player, fruit, position, points: fruit.points() });
+        } else if fruit.tick(delta) {
+            self.fruit = None;
+        }
+    }
+
+    fn weaken_ghosts(&mut self) {
+        // reset the consecutive kills value
+        self.consecutive_kills = 0;
+
+        // send the ghosts into weakened mode
+        for ghost in self.ghosts.iter_mut() {
+            if let GhostPositionStatus::OutAndAbout = ghost.position_status {
+                ghost.actions_status = GhostActionsStatus::Weakened;
+
+                if self.frightened_behaviour == FrightenedBehaviour::Wander {
+                    // turn straight around, and let the ghost make a new decision even if it has just made one here
+                    ghost.direction_of_travel = ghost.direction_of_travel.opposite();
+                    ghost.last_decision_point = Vec2::splat(-1.0);
+                }
+
+                ghost.time_weakened = Some(Timer::from_seconds(self.settings.frightened_time, TimerMode::Once));
+            }
+        }
+    }
+
+    fn update_ghost_waves(&mut self, delta: Duration) {
+        // the waves are paused while the ghosts are weakened
+        if self.ghosts.iter().any(|ghost| ghost.actions_status == GhostActionsStatus::Weakened) {
+            return;
+        }
+
+        if self.ghost_waves.tick(delta) {
+            let mode = self.ghost_waves.mode();
+            info!("Ghosts now in {:?} mode", mode);
+
+            for ghost in self.ghosts.iter_mut() {
+                if let GhostActionsStatus::Scattering | GhostActionsStatus::SearchingForPlayer = ghost.actions_status {
+                    ghost.actions_status = roaming_status(mode);
+
+                    // every ghost turns around when the mode changes
+                    ghost.direction_of_travel = ghost.direction_of_travel.opposite();
+                    // let the ghost make a new decision even if it has just made one at this point
+                    ghost.last_decision_point = Vec2::splat(-1.0);
+                }
+            }
+        }
+    }
+
+    fn move_ghosts(&mut self, delta: Duration) {
+        // collect where all the ghosts are before any of them move, some ghosts target based on where the others are
+        let ghost_positions: Vec<(String, Vec2)> = self.ghosts.iter()
+            .map(|ghost| (ghost.name.clone(), ghost.position))
+            .collect();
+
+        for ghost in self.ghosts.iter_mut() {
+            // each ghost goes after (or runs away from) whichever player is nearest to it
+            let player = self.players.iter()
+                .filter(|player| player.in_play)
+                .min_by(|a, b| a.position.distance_squared(ghost.position).total_cmp(&b.position.distance_squared(ghost.position)))
+                .unwrap_or(&self.players[0]);
+
+            let context = GhostContext {
+                game_logic: &self.board,
+                player_pos: player.position,
+                player_direction: player.direction_of_travel,
+                ghost_positions: &ghost_positions,
+                wave_mode: self.ghost_waves.mode(),
+                frightened_behaviour: self.frightened_behaviour,
+            };
+
+            ghost.update(&context, &mut self.pathfinder, &mut self.rng, delta);
+        }
+    }
+
+    fn check_ghost_player_collision(&mut self, events: &mut Vec<SimulationEvent>) {
+        for player in 0..self.players.len() {
+            if self.players[player].in_play {
+                self.check_ghost_collision(player, events);
+            }
+        }
+    }
+
+    fn check_ghost_collision(&mut self, player: usize, events: &mut Vec<SimulationEvent>) {
+        let player_rect = Rect::from_center_size(self.players[player].position, Vec2::splat(CHARACTER_SIZE));
+
+        for (index, ghost) in self.ghosts.iter_mut().enumerate() {
+            if !check_collision(player_rect, Rect::from_center_size(ghost.position, Vec2::splat(CHARACTER_SIZE))) {
+                continue;
+            }
+
+            // collision detected
+            match ghost.actions_status {
+                GhostActionsStatus::Weakened => {
+                    ghost.actions_status = GhostActionsStatus::RunningToPen;
+                    ghost.position_status = GhostPositionStatus::ReturningToPen;
+
+                    ghost.time_weakened = None;
+
+                    // no cap on the count, any kills past the end of the combo table get the last entry's points
+                    self.consecutive_kills += 1;
+                    events.push(SimulationEvent::GhostEaten {
+                        player,
+                        ghost: index,
+                        position: ghost.position,
+                        points: self.scoring.ghost_points(self.consecutive_kills),
+                    });
+                    self.freeze = Some(Timer::from_seconds(GHOST_EATEN_FREEZE_TIME, TimerMode::Once));
+                },
+                GhostActionsStatus::RunningToPen => {
+                    // ghost is running to pen - do nothing
+                    // if we wanted we could do something here - stall the ghost while it runs home? etc.
+                },
+                GhostActionsStatus::SearchingForPlayer | GhostActionsStatus::Scattering
+                    if self.status == SimulationStatus::Playing && self.players[player].in_play => {
+                    // all other times - touching a ghost means lose a life
+                    let others_in_play = self.players.iter().enumerate().any(|(other, state)| other != player && state.in_play);
+                    if others_in_play {
+                        // only this player leaves the board, everyone else carries on
+                        self.players[player].in_play = false;
+                    } else {
+                        self.status = SimulationStatus::PlayerCaught;
+                    }
+                    events.push(SimulationEvent::PlayerCaught { player });
+                },
+                _ => {
+
+                }
+            }
+        }
+    }
+
+    fn update_weakened_timers(&mut self, delta: Duration) {
+        let mode = self.ghost_waves.mode();
+
+        for ghost in self.ghosts.iter_mut() {
+            if let Some(timer) = &mut ghost.time_weakened {
+                timer.tick(delta);
+
+                if timer.finished() {
+                    // change this ghost back to normal
+                    ghost.actions_status = roaming_status(mode);
+                    ghost.time_weakened = None;
+                }
+            }
+        }
+    }
+}
+
+/*
+ * Close enough to the middle of a tile to turn down a side path - cornering lets the turn start (or finish) a little
+ * further away from the middle
+ */
+fn can_turn(position: Vec2, direction_of_travel: Direction, cornering: bool) -> bool {
+    if !cornering {
+        return at_decision_point(position, direction_of_travel);
+    }
+
+    let along = if direction_of_travel.horizontal == Horizontal::Zero { position.y } else { position.x };
+    (along - along.round()).abs() < CORNERING_THRESHOLD
+}
+
+fn move_towards(value: f32, target: f32, amount: f32) -> f32 {
+    if value < target {
+        (value + amount).min(target)
+    } else {
+        (value - amount).max(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::*;
+    use crate::maze::parse_maze;
+
+    const TICK: Duration = Duration::from_nanos(16_666_667);
+
+    const UP: Direction = Direction { vertical: Vertical::Up, horizontal: Horizontal::Zero };
+    const DOWN: Direction = Direction { vertical: Vertical::Down, horizontal: Horizontal::Zero };
+    const LEFT: Direction = Direction { vertical: Vertical::Zero, horizontal: Horizontal::Left };
+    const RIGHT: Direction = Direction { vertical: Vertical::Zero, horizontal: Horizontal::Right };
+
+    // a loop with a point token just to the right of the start and a ghost weakness token in the bottom right corner
+    const SMALL_MAZE: &str = "width: 7
+height: 5
+player_start: 1, 1
+pen_exit: 5, 3
+pen_centre: 3, 2
+---
+#######
+#_.___#
+#_###_#
+#____o#
+#######
+";
+
+    fn board(source: &str) -> GameLogic {
+        GameLogic::from_maze(&parse_maze(source).unwrap())
+    }
+
+    // the ghosts stay in the pen for as long as these tests take
+    fn small_simulation() -> Simulation {
+        let mut simulation = Simulation::new(board(SMALL_MAZE), 1);
+        simulation.settings.pen_release = [1000.0; 4];
+        simulation.reset_positions();
+        simulation
+    }
+
+    // hold the direction down until something is eaten, giving up after a few seconds
+    fn tick_until_eaten(simulation: &mut Simulation, input: Direction) -> Vec<SimulationEvent> {
+        for _ in 0..300 {
+            let events = simulation.tick(input, TICK);
+            if !events.is_empty() {
+                return events;
+            }
+        }
+        panic!("nothing was eaten");
+    }
+
+    #[test]
+    fn same_seed_and_inputs_give_the_same_checksum() {
+        let play = || {
+            let mut simulation = Simulation::new(board(include_str!("../assets/levels/classic.maze")), 42);
+            let mut inputs = StdRng::seed_from_u64(42);
+            let mut input = Direction::ZERO;
+            let mut checksums = Vec::new();
+
+            for tick in 0..10_000 {
+                if inputs.gen_bool(0.05) {
+                    input = [UP, DOWN, LEFT, RIGHT][inputs.gen_range(0..4)];
+                }
+                simulation.tick(input, TICK);
+                if simulation.status == SimulationStatus::PlayerCaught {
+                    simulation.reset_positions();
+                }
+                if tick % 500 == 0 {
+                    checksums.push(simulation.checksum());
+                }
+            }
+
+            checksums.push(simulation.checksum());
+            checksums
+        };
+
+        assert_eq!(play(), play());
+    }
+
+    #[test]
+    fn eating_a_token() {
+        let mut simulation = small_simulation();
+        assert_eq!(simulation.tokens_left(), 2);
+
+        let events = tick_until_eaten(&mut simulation, RIGHT);
+
+        assert_eq!(events, vec![SimulationEvent::TokenEaten {
+            player: 0,
+            cell: IVec2::new(2, 1),
+            reward: BlockReward::PointToken,
+            points: simulation.scoring.point_token,
+        }]);
+        assert_eq!(simulation.score, simulation.scoring.point_token);
+        assert_eq!(simulation.players[0].score, simulation.scoring.point_token);
+        assert_eq!(simulation.tokens_left(), 1);
+        assert!(matches!(simulation.board.get_block(2, 1).unwrap().block_reward, BlockReward::Nothing));
+        assert_eq!(simulation.status, SimulationStatus::Playing);
+    }
+
+    #[test]
+    fn level_cleared_when_the_last_token_goes() {
+        let mut simulation = small_simulation();
+        tick_until_eaten(&mut simulation, RIGHT);
+
+        // along to the end of the top row, then down to the ghost weakness token
+        let events = tick_until_eaten(&mut simulation, DOWN);
+
+        assert!(matches!(events[0], SimulationEvent::TokenEaten { reward: BlockReward::GhostWeaknessToken, .. }));
+        assert_eq!(events.last(), Some(&SimulationEvent::LevelCleared));
+        assert_eq!(simulation.tokens_left(), 0);
+        assert_eq!(simulation.status, SimulationStatus::LevelCleared);
+        assert_eq!(simulation.score, simulation.scoring.point_token + simulation.scoring.ghost_weakness_token);
+
+        // nothing moves once the level is over
+        let checksum = simulation.checksum();
+        assert!(simulation.tick(LEFT, TICK).is_empty());
+        assert_eq!(simulation.checksum(), checksum);
+    }
+
+    #[test]
+    fn player_caught_then_reset_positions() {
+        let mut simulation = small_simulation();
+        tick_until_eaten(&mut simulation, RIGHT);
+        let score = simulation.score;
+
+        // put a chasing ghost right on top of the player
+        let ghost_start = small_simulation().ghosts[0].position;
+        simulation.ghosts[0].actions_status = GhostActionsStatus::SearchingForPlayer;
+        simulation.ghosts[0].position = simulation.players[0].position;
+
+        let events = simulation.tick(Direction::ZERO, TICK);
+        assert!(events.contains(&SimulationEvent::PlayerCaught { player: 0 }));
+        assert_eq!(simulation.status, SimulationStatus::PlayerCaught);
+        // everything stays where it is until the positions are reset
+        assert!(simulation.tick(RIGHT, TICK).is_empty());
+
+        simulation.reset_positions();
+
+        assert_eq!(simulation.status, SimulationStatus::Playing);
+        assert_eq!(simulation.players[0].position, simulation.board.player_start);
+        assert_eq!(simulation.ghosts[0].position, ghost_start);
+        assert_eq!(simulation.ghosts[0].actions_status, GhostActionsStatus::Idle);
+        // the tokens and score are left alone
+        assert_eq!(simulation.tokens_left(), 1);
+        assert_eq!(simulation.score, score);
+    }
+
+    #[test]
+    fn caught_player_leaves_the_board_while_the_other_plays_on() {
+        let mut simulation = small_simulation();
+        simulation.add_player();
+        simulation.players[1].position = Vec2::new(1.0, 3.0);
+
+        simulation.ghosts[0].actions_status = GhostActionsStatus::SearchingForPlayer;
+        simulation.ghosts[0].position = simulation.players[0].position;
+
+        let events = simulation.tick_players(&[Direction::ZERO, Direction::ZERO], TICK);
+        assert!(events.contains(&SimulationEvent::PlayerCaught { player: 0 }));
+        assert_eq!(simulation.status, SimulationStatus::Playing);
+        assert!(!simulation.players[0].in_play);
+        assert!(simulation.players[1].in_play);
+    }
+
+    #[test]
+    fn steering_a_ghost_diagonally_picks_the_turn() {
+        let mut simulation = small_simulation();
+        simulation.control_ghost("Blinky");
+        let blinky = simulation.human_ghost.unwrap();
+        let up_left = Direction { vertical: Vertical::Up, horizontal: Horizontal::Left };
+        let human_direction = |simulation: &Simulation| {
+            let direction = simulation.ghosts[blinky].human_direction;
+            (direction.vertical, direction.horizontal)
+        };
+
+        simulation.ghosts[blinky].direction_of_travel = RIGHT;
+        simulation.steer_ghost(up_left);
+        assert_eq!(human_direction(&simulation), (Vertical::Up, Horizontal::Zero));
+
+        simulation.ghosts[blinky].direction_of_travel = DOWN;
+        simulation.steer_ghost(up_left);
+        assert_eq!(human_direction(&simulation), (Vertical::Zero, Horizontal::Left));
+
+        // letting go keeps the last way pressed
+        simulation.steer_ghost(Direction::ZERO);
+        assert_eq!(human_direction(&simulation), (Vertical::Zero, Horizontal::Left));
+    }
+
+    #[test]
+    fn ghost_combo_table() {
+        let combo = Scoring::parse_ghost_combo(" 100  300 900").unwrap();
+        assert_eq!(combo, vec![100, 300, 900]);
+        assert_eq!(Scoring::ghost_combo_text(&combo), "100 300 900");
+
+        let scoring = Scoring { ghost_combo: combo, ..Scoring::default() };
+        assert_eq!(scoring.ghost_points(1), 100);
+        assert_eq!(scoring.ghost_points(3), 900);
+        // any past the end of the table get the last entry
+        assert_eq!(scoring.ghost_points(5), 900);
+
+        assert_eq!(Scoring::parse_ghost_combo(""), None);
+        assert_eq!(Scoring::parse_ghost_combo("200 four"), None);
+        assert_eq!(Scoring::parse_ghost_combo("200 -400"), None);
+    }
+
+    #[test]
+    fn plays_many_whole_games() {
+        let classic = board(include_str!("../assets/levels/classic.maze"));
+
+        for seed in 0..100 {
+            let mut simulation = Simulation::new(classic.clone(), seed);
+            let mut inputs = StdRng::seed_from_u64(seed);
+            let mut input = Direction::ZERO;
+            let mut lives = 3;
+            let mut points = 0;
+
+            for tick in 0..30_000 {
+                if inputs.gen_bool(0.05) {
+                    input = [UP, DOWN, LEFT, RIGHT][inputs.gen_range(0..4)];
+                }
+
+                for event in simulation.tick(input, TICK) {
+                    if let SimulationEvent::TokenEaten { points: event_points, .. } | SimulationEvent::GhostEaten { points: event_points, .. } |
+                        SimulationEvent::FruitEaten { points: event_points, .. } = event {
+                        points += event_points;
+                    }
+                }
+
+                match simulation.status {
+                    SimulationStatus::Playing => {},
+                    SimulationStatus::PlayerCaught => {
+                        lives -= 1;
+                        if lives == 0 {
+                            break;
+                        }
+                        simulation.reset_positions();
+                    },
+                    SimulationStatus::LevelCleared => {
+                        simulation.start_level(simulation.level_board.clone(), simulation.level + 1);
+                    },
+                }
+
+                if tick % 1000 == 0 {
+                    let tokens_on_board = simulation.board.game_blocks.iter()
+                        .filter(|block| !matches!(block.block_reward, BlockReward::Nothing))
+                        .count();
+                    assert_eq!(simulation.tokens_left(), tokens_on_board, "seed {}", seed);
+                }
+            }
+
+            // every point scored came with an event saying so
+            assert_eq!(simulation.score, points, "seed {}", seed);
+            assert_eq!(simulation.players[0].score, points, "seed {}", seed);
+        }
+    }
+}
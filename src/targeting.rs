@@ -0,0 +1,195 @@
+use bevy::prelude::*;
+
+use crate::gamelogic::Direction;
+
+/*
+ * Ghost targeting strategies
+ *
+ * While chasing the player each ghost aims for a target tile given by its strategy, this is what gives the
+ * ghosts their different personalities. Strategies only work with gameboard positions so they can be swapped
+ * around or checked on their own without the rest of the game.
+ */
+
+/*
+ * Everything a strategy can use to pick its target (all positions are gameboard coordinates)
+ */
+pub struct TargetingContext<'a> {
+    pub ghost_pos: Vec2,
+    pub player_pos: Vec2,
+    pub player_direction: Direction,
+    // the positions of all of the ghosts on the board by name, for strategies that work with another ghost
+    pub ghost_positions: &'a [(String, Vec2)],
+}
+
+impl TargetingContext<'_> {
+    pub fn ghost_position(&self, name: &str) -> Option<Vec2> {
+        self.ghost_positions.iter()
+            .find(|(ghost_name, _)| ghost_name == name)
+            .map(|(_, position)| *position)
+    }
+
+    /*
+     * The position the given number of tiles in front of the player (in the direction they are moving)
+     */
+    pub fn ahead_of_player(&self, tiles: f32) -> Vec2 {
+        self.player_pos + Vec2 {
+            x: self.player_direction.horizontal as i32 as f32 * tiles,
+            y: self.player_direction.vertical as i32 as f32 * tiles,
+        }
+    }
+}
+
+pub trait TargetingStrategy: Send + Sync {
+    fn target(&self, context: &TargetingContext) -> Vec2;
+}
+
+/*
+ * Blinky - heads straight for the player
+ */
+pub struct DirectChase;
+
+impl TargetingStrategy for DirectChase {
+    fn target(&self, context: &TargetingContext) -> Vec2 {
+        context.player_pos
+    }
+}
+
+/*
+ * Pinky - aims for a few tiles in front of the player to cut them off
+ */
+pub struct Ambush {
+    pub tiles_ahead: f32,
+}
+
+impl TargetingStrategy for Ambush {
+    fn target(&self, context: &TargetingContext) -> Vec2 {
+        context.ahead_of_player(self.tiles_ahead)
+    }
+}
+
+/*
+ * Inky - takes the vector from the partner ghost to a point just ahead of the player and doubles it,
+ * so the two ghosts end up closing in on the player from opposite sides
+ */
+pub struct Flank {
+    pub partner: String,
+    pub tiles_ahead: f32,
+}
+
+impl TargetingStrategy for Flank {
+    fn target(&self, context: &TargetingContext) -> Vec2 {
+        let pivot = context.ahead_of_player(self.tiles_ahead);
+
+        match context.ghost_position(&self.partner) {
+            Some(partner_pos) => pivot + (pivot - partner_pos),
+            // nobody to flank with - just go for the pivot point
+            None => pivot,
+        }
+    }
+}
+
+/*
+ * Clyde - chases the player like Blinky until he gets close, then gets scared and retreats to his corner
+ */
+pub struct Shy {
+    pub retreat_distance: f32,
+    pub retreat_pos: Vec2,
+}
+
+impl TargetingStrategy for Shy {
+    fn target(&self, context: &TargetingContext) -> Vec2 {
+        if context.ghost_pos.distance(context.player_pos) < self.retreat_distance {
+            self.retreat_pos
+        } else {
+            context.player_pos
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gamelogic::{GameLogic, Horizontal, Vertical};
+    use crate::ghost::{new_ghosts, Ghost};
+    use crate::levels::LevelSettings;
+    use crate::maze::parse_maze;
+
+    const RIGHT: Direction = Direction { vertical: Vertical::Zero, horizontal: Horizontal::Right };
+    const UP: Direction = Direction { vertical: Vertical::Up, horizontal: Horizontal::Zero };
+
+    // an open 12 by 12 room, the scatter corners default to the corners of the board
+    fn test_ghosts() -> (GameLogic, Vec<Ghost>) {
+        let mut source = String::from("width: 12\nheight: 12\nplayer_start: 5, 8\npen_exit: 5, 3\npen_centre: 5, 5\n---\n");
+        source.push_str("############\n");
+        for _ in 0..10 {
+            source.push_str("#__________#\n");
+        }
+        source.push_str("############\n");
+
+        let game_logic = GameLogic::from_maze(&parse_maze(&source).unwrap());
+        let ghosts = new_ghosts(&game_logic, &LevelSettings::for_level(1));
+        (game_logic, ghosts)
+    }
+
+    fn target(ghosts: &[Ghost], name: &str, context: &TargetingContext) -> Vec2 {
+        ghosts.iter().find(|ghost| ghost.name == name).unwrap().targeting.target(context)
+    }
+
+    fn context(ghost_pos: Vec2, player_pos: Vec2, player_direction: Direction, ghost_positions: &[(String, Vec2)]) -> TargetingContext<'_> {
+        TargetingContext { ghost_pos, player_pos, player_direction, ghost_positions }
+    }
+
+    #[test]
+    fn blinky_targets_the_player() {
+        let (_, ghosts) = test_ghosts();
+        let context = context(Vec2::new(2.0, 2.0), Vec2::new(6.0, 7.0), RIGHT, &[]);
+
+        assert_eq!(target(&ghosts, "Blinky", &context), Vec2::new(6.0, 7.0));
+    }
+
+    #[test]
+    fn pinky_targets_four_tiles_ahead() {
+        let (_, ghosts) = test_ghosts();
+
+        let context_right = context(Vec2::new(2.0, 2.0), Vec2::new(3.0, 6.0), RIGHT, &[]);
+        assert_eq!(target(&ghosts, "Pinky", &context_right), Vec2::new(7.0, 6.0));
+
+        let context_up = context(Vec2::new(2.0, 2.0), Vec2::new(3.0, 6.0), UP, &[]);
+        assert_eq!(target(&ghosts, "Pinky", &context_up), Vec2::new(3.0, 2.0));
+    }
+
+    #[test]
+    fn inky_mirrors_blinky_through_two_tiles_ahead() {
+        let (_, ghosts) = test_ghosts();
+        let blinky = Vec2::new(3.0, 4.0);
+        let ghost_positions = [(String::from("Blinky"), blinky)];
+        let context = context(Vec2::new(9.0, 9.0), Vec2::new(6.0, 6.0), RIGHT, &ghost_positions);
+
+        let pivot = Vec2::new(8.0, 6.0);
+        let inky_target = target(&ghosts, "Inky", &context);
+        assert_eq!(inky_target, Vec2::new(13.0, 8.0));
+        // the pivot is half way between Blinky and the target
+        assert_eq!((blinky + inky_target) / 2.0, pivot);
+    }
+
+    #[test]
+    fn inky_without_blinky_targets_the_pivot() {
+        let (_, ghosts) = test_ghosts();
+        let context = context(Vec2::new(9.0, 9.0), Vec2::new(6.0, 6.0), RIGHT, &[]);
+
+        assert_eq!(target(&ghosts, "Inky", &context), Vec2::new(8.0, 6.0));
+    }
+
+    #[test]
+    fn clyde_retreats_within_eight_tiles() {
+        let (game_logic, ghosts) = test_ghosts();
+        let player_pos = Vec2::new(1.0, 1.0);
+
+        let far = context(Vec2::new(10.0, 1.0), player_pos, RIGHT, &[]);
+        assert_eq!(target(&ghosts, "Clyde", &far), player_pos);
+
+        let close = context(Vec2::new(8.0, 1.0), player_pos, RIGHT, &[]);
+        assert_eq!(target(&ghosts, "Clyde", &close), game_logic.scatter_corners.clyde);
+        assert_eq!(game_logic.scatter_corners.clyde, Vec2::new(0.0, 11.0));
+    }
+}
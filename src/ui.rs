@@ -1,7 +1,10 @@
 use bevy::prelude::*;
 //use bevy_inspector_egui::egui::Ui;
 
-use crate::{gamelogic::OnGameplayScreen, gamestates::despawn_screen, GameState, LivesLeft, Score};
+use crate::{fruit::Fruit, fruitgraphics::FruitImages, gamelogic::{GameSimulation, OnGameplayScreen}, gamestates::despawn_screen, players::Players, inputactions::{ActionInput, InputAction}, GameState, LivesLeft, Score, MAX_LIVES};
+
+// how many of the most recently eaten fruit are shown along the bottom
+const FRUIT_HISTORY_LENGTH: usize = 7;
 
 #[derive(Component)]
 pub struct OnLevelCompleteScreen;
@@ -12,18 +15,33 @@ pub struct HeartLife(pub i32);
 #[derive(Component)]
 pub struct HeartContainer;
 
+// a heart that blinks for a while after it has been added
+#[derive(Component)]
+pub struct HeartFlash(Timer);
+
+const HEART_FLASH_TIME: f32 = 2.0;
+const HEART_FLASH_RATE: f32 = 0.15;
+
 #[derive(Component)]
 pub struct ScoreText;
 
+#[derive(Component)]
+pub struct FruitHistoryContainer;
+
 pub struct GameUI;
 
 impl Plugin for GameUI {
     fn build(&self, app: &mut App) {
         app.add_systems(Startup, spawn_game_ui)
-            .add_systems(OnEnter(GameState::LevelSetup), spawn_hearts_ui)
-            .add_systems(Update, update_score_ui)
+            .add_systems(OnEnter(GameState::GameStart), spawn_hearts_ui)
+            .add_systems(OnEnter(GameState::MainMenu), spawn_hearts_ui)
+            .add_systems(Update, (update_score_ui, flash_hearts))
+            .add_systems(Update, update_fruit_history_ui.run_if(resource_exists::<GameSimulation>))
             .add_systems(OnExit(GameState::LoseLife), update_lives_ui)
+            // a player caught in co-op uses up a life without leaving gameplay
+            .add_systems(Update, update_lives_ui.run_if(in_state(GameState::Gameplay).and_then(resource_changed::<LivesLeft>)))
             .add_systems(OnEnter(GameState::LevelComplete), (despawn_screen::<OnGameplayScreen>, setup_level_complete, add_life_ui).chain())
+            .add_systems(Update, check_next_level_pressed.run_if(in_state(GameState::LevelComplete)))
             .add_systems(OnExit(GameState::LevelComplete), despawn_screen::<OnLevelCompleteScreen>);
     }
 }
@@ -92,29 +110,99 @@ fn spawn_game_ui(
                 HeartContainer,
             ));
         });
+
+    // fruit history along the bottom, newest on the right
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                bottom: Val::Px(0.0),
+                width: Val::Percent(100.0),
+                height: Val::Px(20.0),
+                flex_direction: FlexDirection::Row,
+                align_items: AlignItems::Center,
+                justify_content: JustifyContent::End,
+                padding: UiRect::axes(Val::Px(10.0), Val::Px(0.0)),
+                ..default()
+            },
+            background_color: Color::BLACK.into(),
+            z_index: ZIndex::Local(1),
+            ..default()
+        },
+        FruitHistoryContainer,
+    ));
 }
 
+/*
+ * Show a heart for each life left, in place of whatever hearts were there before
+ */
 fn spawn_hearts_ui(
     heart_container: Query<Entity, With<HeartContainer>>,
     mut commands: Commands,
     asset_server: Res<AssetServer>,
+    lives_left: Res<LivesLeft>,
 ) {
-    commands.entity(heart_container.single()).with_children( | container | {
-        for i in 0..3 {
-            let icon = asset_server.load("Heart.png");
-            container.spawn((
-                ImageBundle {
-                    style: Style {
-                        width: Val::Px(20.0),
-                        ..default()
-                    },
-                    image: UiImage::new(icon),
-                    ..default()
-                },
-                HeartLife(i),
-            ));
+    let Ok(heart_container) = heart_container.get_single() else {
+        return;
+    };
+    commands.entity(heart_container).despawn_descendants();
+
+    for i in 0..lives_left.0.min(MAX_LIVES) {
+        spawn_heart(&mut commands, heart_container, &asset_server, i);
+    }
+}
+
+/*
+ * Add a heart for the given life to the end of the heart container
+ */
+pub fn spawn_heart(
+    commands: &mut Commands,
+    heart_container: Entity,
+    asset_server: &AssetServer,
+    life: i32,
+) -> Entity {
+    let icon = asset_server.load("Heart.png");
+
+    let heart = commands.spawn((
+        ImageBundle {
+            style: Style {
+                width: Val::Px(20.0),
+                ..default()
+            },
+            image: UiImage::new(icon),
+            ..default()
+        },
+        HeartLife(life),
+    )).id();
+
+    commands.entity(heart_container).add_child(heart);
+    heart
+}
+
+/*
+ * Make the heart blink so that the new life gets noticed
+ */
+pub fn flash_heart(commands: &mut Commands, heart: Entity) {
+    commands.entity(heart).insert(HeartFlash(Timer::from_seconds(HEART_FLASH_TIME, TimerMode::Once)));
+}
+
+fn flash_hearts(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut hearts: Query<(Entity, &mut HeartFlash, &mut Visibility)>,
+) {
+    for (entity, mut flash, mut visibility) in &mut hearts {
+        flash.0.tick(time.delta());
+
+        if flash.0.finished() {
+            *visibility = Visibility::Inherited;
+            commands.entity(entity).remove::<HeartFlash>();
+            continue;
         }
-    });
+
+        let flash_count = (flash.0.elapsed_secs() / HEART_FLASH_RATE) as u32;
+        *visibility = if flash_count.is_multiple_of(2) { Visibility::Hidden } else { Visibility::Inherited };
+    }
 }
 
 
@@ -124,8 +212,8 @@ fn setup_level_complete(
 ) {
     // increase lives left by 1
     lives_left.0 += 1;
-    if lives_left.0 > 5 {
-        lives_left.0 = 5;
+    if lives_left.0 > MAX_LIVES {
+        lives_left.0 = MAX_LIVES;
     }
 
     // show the screen
@@ -162,9 +250,34 @@ fn setup_level_complete(
     });
 }
 
-fn update_score_ui(mut texts: Query<&mut Text, With<ScoreText>>, score: Res<Score>) {
+fn check_next_level_pressed(
+    mut game_state: ResMut<NextState<GameState>>,
+    input: ActionInput,
+) {
+    if input.just_pressed(InputAction::Start) {
+        game_state.set(GameState::LevelSetup);
+    }
+}
+
+fn update_score_ui(mut texts: Query<&mut Text, With<ScoreText>>, score: Res<Score>, players: Res<Players>) {
     for mut text in &mut texts {
-        text.sections[0].value = format!("Score: {:?}", score.0);
+        if players.count < 2 {
+            text.sections.truncate(1);
+            text.sections[0].value = format!("Score: {:?}", score.0);
+            text.sections[0].style.color = Color::WHITE;
+            continue;
+        }
+
+        // 1UP and 2UP side by side, the player playing now in white and the other greyed out while they wait their turn
+        let style = text.sections[0].style.clone();
+        text.sections = players.scores(score.0).iter().enumerate().map(|(player, player_score)| {
+            TextSection::new(
+                format!("{}UP {}  ", player + 1, player_score),
+                TextStyle {
+                    color: if !players.alternating() || player == players.current { Color::WHITE } else { Color::srgb(0.5, 0.5, 0.5) },
+                    ..style.clone()
+                })
+        }).collect();
     }
 }
 
@@ -188,20 +301,39 @@ fn add_life_ui(
     lives_left: Res<LivesLeft>,
     asset_server: Res<AssetServer>,
 ) {
-    if hearts.iter().count() < 5 {
-        let icon = asset_server.load("Heart.png");
-        commands.entity(heart_container.single()).with_children( |container | {
-            container.spawn((
-                ImageBundle {
-                    style: Style {
-                        width: Val::Px(20.0),
-                        ..default()
-                    },
-                    image: UiImage::new(icon),
+    if (hearts.iter().count() as i32) < MAX_LIVES {
+        spawn_heart(&mut commands, heart_container.single(), &asset_server, lives_left.0 - 1);
+    }
+}
+fn update_fruit_history_ui(
+    mut commands: Commands,
+    simulation: Res<GameSimulation>,
+    fruit_history_container: Query<Entity, With<FruitHistoryContainer>>,
+    fruit_images: Res<FruitImages>,
+    mut shown: Local<Vec<Fruit>>,
+) {
+    let collected = &simulation.collected_fruit;
+    let recent = &collected[collected.len().saturating_sub(FRUIT_HISTORY_LENGTH)..];
+    if shown.as_slice() == recent {
+        return;
+    }
+    *shown = recent.to_vec();
+
+    let Ok(container) = fruit_history_container.get_single() else {
+        return;
+    };
+
+    commands.entity(container).despawn_descendants().with_children(|container| {
+        for fruit in recent {
+            container.spawn(ImageBundle {
+                style: Style {
+                    width: Val::Px(20.0),
+                    height: Val::Px(20.0),
                     ..default()
                 },
-                HeartLife(lives_left.0 - 1),
-            ));
-        });
-    }
-}
\ No newline at end of file
+                image: UiImage::new(fruit_images.0[fruit].clone()),
+                ..default()
+            });
+        }
+    });
+}
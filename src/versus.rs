@@ -0,0 +1,103 @@
+use bevy::prelude::*;
+
+use crate::gamestates::{despawn_screen, GameState};
+use crate::inputactions::{ActionInput, InputAction};
+use crate::{LivesLeft, Score};
+
+/*
+ * Versus matches
+ *
+ * One player is Pac-Man and a second player steers Blinky, picking which way it goes at each junction (see ghost.rs)
+ * while the other ghosts are left to themselves. Pac-Man wins by clearing the maze and the ghosts win by catching
+ * Pac-Man on the last life - either way the match is over and the winner is shown before going back to the main menu.
+ */
+
+// the ghost the second player takes over
+pub const VERSUS_GHOST: &str = "Blinky";
+
+#[derive(Resource, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MatchWinner {
+    PacMan,
+    Ghosts,
+}
+
+impl MatchWinner {
+    fn message(&self) -> &'static str {
+        match self {
+            MatchWinner::PacMan => "PAC-MAN WINS!\n\nThe maze has been cleared",
+            MatchWinner::Ghosts => "GHOSTS WIN!\n\nPac-Man has been caught for the last time",
+        }
+    }
+
+    fn colour(&self) -> Color {
+        match self {
+            MatchWinner::PacMan => Color::srgb(1.0, 1.0, 0.0),
+            MatchWinner::Ghosts => Color::srgb(1.0, 0.0, 0.0),
+        }
+    }
+}
+
+#[derive(Component)]
+struct OnMatchOverScreen;
+
+pub struct VersusPlugin;
+
+impl Plugin for VersusPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(OnEnter(GameState::MatchOver), setup_match_over)
+            .add_systems(Update, match_over_input.run_if(in_state(GameState::MatchOver)))
+            .add_systems(OnExit(GameState::MatchOver), despawn_screen::<OnMatchOverScreen>);
+    }
+}
+
+fn setup_match_over(
+    mut commands: Commands,
+    winner: Res<MatchWinner>,
+) {
+    commands.spawn(
+        (
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.0),
+                    height: Val::Percent(100.0),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    padding: UiRect::all(Val::Px(10.0)),
+                    ..default()
+                },
+                background_color: Color::BLACK.into(),
+                ..default()
+            },
+            OnMatchOverScreen,
+        )
+    ).with_children(|commands| {
+        commands.spawn(
+            TextBundle {
+                text: Text::from_section(
+                    format!("{}\n\nPress start", winner.message()),
+                    TextStyle {
+                        font_size: 20.0,
+                        color: winner.colour(),
+                        ..default()
+                    }
+                )
+                .with_justify(JustifyText::Center),
+                ..default()
+            },
+        );
+    });
+}
+
+fn match_over_input(
+    input: ActionInput,
+    mut lives_left: ResMut<LivesLeft>,
+    mut score: ResMut<Score>,
+    mut game_state: ResMut<NextState<GameState>>,
+) {
+    if input.just_pressed(InputAction::Start) {
+        // no lives left makes the next game a new one
+        lives_left.0 = 0;
+        score.0 = 0;
+        game_state.set(GameState::MainMenu);
+    }
+}